@@ -0,0 +1,58 @@
+extern crate parsely;
+
+use parsely::json2::{JsonEvent2, JsonParserBuilder};
+use std::hint::black_box;
+use std::time::Instant;
+
+// Run with `cargo bench`; prints the throughput figures quoted in the readme
+// for a generated document of objects, strings and numbers.  The second figure
+// asks for the path of every event, the first never does, so it should cost
+// nothing there.
+
+fn document(records: usize) -> Vec<u8> {
+    let mut doc = String::from("[");
+    for i in 0..records {
+        if i > 0 {
+            doc.push(',');
+        }
+        doc.push_str(&format!(
+            "{{\"id\":{},\"name\":\"record number {}\",\"score\":{}.{},\"tags\":[\"a\",\"b\\n\",\"c\"],\"active\":{},\"next\":null}}",
+            i, i, i % 1000, i % 97, i % 2 == 0
+        ));
+    }
+    doc.push(']');
+    doc.into_bytes()
+}
+
+fn parse(data: &[u8], paths: bool) -> usize {
+    let mut json = JsonParserBuilder::new().buffer_size(1024 * 128).build(data);
+    let mut count = 0;
+    while let Some(e) = json.next_token().unwrap() {
+        if let JsonEvent2::String(_) | JsonEvent2::Number(_) = e {
+            count += 1;
+        }
+        if paths {
+            black_box(json.current_path());
+        }
+    }
+    count
+}
+
+fn measure(name: &str, data: &[u8], paths: bool) {
+    let mb = data.len() as f64 / (1024.0 * 1024.0);
+    let mut best = f64::MAX;
+    for _i in 0..10 {
+        let start_time = Instant::now();
+        let count = parse(data, paths);
+        let time_s = start_time.elapsed().as_secs_f64();
+        assert_eq!(200_000 * 6, count);
+        best = best.min(time_s);
+    }
+    println!("{} {:.1} MB: best {:.3} s, {:.0} MB/sec", name, mb, best, mb / best);
+}
+
+fn main() {
+    let data = document(200_000);
+    measure("parse", &data, false);
+    measure("parse, with paths", &data, true);
+}
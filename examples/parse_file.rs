@@ -1,8 +1,7 @@
 extern crate parsely;
 
 
-use parsely::{internals::ParseResult, json2::JsonParser};
-use std::io::BufReader;
+use parsely::{internals::ParseResult, json2::{JsonParser, JsonParserBuilder}};
 use std::time::Instant;
 
 fn main() -> ParseResult<()> {
@@ -13,9 +12,7 @@ fn main() -> ParseResult<()> {
     
     for _i in 0..20 {
         let f = std::fs::File::open( filename ).unwrap();
-        let buf = BufReader::with_capacity(1024 * 128, f);
-
-        let json = JsonParser::new(buf);
+        let json = JsonParserBuilder::new().buffer_size(1024 * 128).build(f);
         let start_time = Instant::now();
         do_parse(json) ?;
         let time_ms = Instant::now().duration_since(start_time).as_millis();
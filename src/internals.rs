@@ -20,8 +20,7 @@ macro_rules! match_or {
                     pr = match result {
                         Ok(_) => { Ok(()) }, 
                         Err(ParseErr::DidNotMatch) => { $r.rewind(mark_or); Err(ParseErr::DidNotMatch)},
-                        Err(ParseErr::Io(x)) => { Err(ParseErr::Io(x)) },
-                        Err(ParseErr::BadData(s)) => { Err(ParseErr::BadData(s))},
+                        Err(e) => { Err(e) },
                     };
                 }
             )* 
@@ -46,8 +45,7 @@ macro_rules! match_all {
                     pr =  match result {
                         Ok(_) => { Ok(()) }, 
                         Err(ParseErr::DidNotMatch) => { $r.rewind(mark_or); Err(ParseErr::DidNotMatch)},
-                        Err(ParseErr::Io(x)) => { Err(ParseErr::Io(x)) },
-                        Err(ParseErr::BadData(s)) => { Err(ParseErr::BadData(s))},
+                        Err(e) => { Err(e) },
                     };
                 }
             )* 
@@ -61,14 +59,239 @@ macro_rules! match_all {
 #[derive(Debug)]
 pub enum ParseErr {
 
-    /// Parser did not match expected input
+    /// Parser did not match expected input, so another option can be tried.
+    /// Only used for backtracking between parsers
     DidNotMatch,
 
+    /// The input is not valid, and why
+    Syntax(ErrorKind),
+
     /// Bad data - plus message.  Some un-recoverable error has occured
     BadData(String),
 
     /// Underlying I/O occured - which is considered fatal
     Io(std::io::Error),
+
+    /// One of the other errors, and where in the input it happened
+    At(Position, Box<ParseErr>),
+
+    /// One of the other errors, and the path (such as `$.orders[3].sku`) to where in
+    /// the document it happened
+    InPath(String, Box<ParseErr>),
+
+    /// An error in a record of a multi-record input (such as JSON lines), with the 
+    /// record number counting from 1
+    InRecord(usize, Box<ParseErr>),
+}
+
+impl ParseErr {
+
+    /// Attaches a position to the error, unless it already has one
+    pub fn at(self, pos: Position) -> ParseErr {
+        match self.position() {
+            Some(_) => self,
+            None => ParseErr::At(pos, Box::new(self)),
+        }
+    }
+
+    /// Attaches the path in the document to the error, unless it already has one
+    pub fn in_path(self, path: String) -> ParseErr {
+        match self.path() {
+            Some(_) => self,
+            None => ParseErr::InPath(path, Box::new(self)),
+        }
+    }
+
+    /// Where in the input the error happened, if known
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            ParseErr::At(pos, _) => Some(*pos),
+            ParseErr::InPath(_, e) | ParseErr::InRecord(_, e) => e.position(),
+            _ => None,
+        }
+    }
+
+    /// Where in the document the error happened, if known and not at the top level
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ParseErr::InPath(path, _) => Some(path),
+            ParseErr::At(_, e) | ParseErr::InRecord(_, e) => e.path(),
+            _ => None,
+        }
+    }
+
+    /// The record the error happened in, for multi-record inputs
+    pub fn record(&self) -> Option<usize> {
+        match self {
+            ParseErr::InRecord(n, _) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The error without any position, path or record
+    pub fn inner(&self) -> &ParseErr {
+        match self {
+            ParseErr::At(_, e) | ParseErr::InPath(_, e) | ParseErr::InRecord(_, e) => e.inner(),
+            e => e,
+        }
+    }
+
+    /// What was wrong with the input, if this is a syntax error
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        match self.inner() {
+            ParseErr::Syntax(kind) => Some(kind),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for ParseErr {
+    fn from(kind: ErrorKind) -> Self {
+        ParseErr::Syntax(kind)
+    }
+}
+
+impl std::fmt::Display for ParseErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErr::DidNotMatch => write!(f, "Input did not match"),
+            ParseErr::Syntax(kind) => write!(f, "{}", kind),
+            ParseErr::BadData(msg) => write!(f, "{}", msg),
+            ParseErr::Io(e) => write!(f, "I/O error, {}", e),
+            ParseErr::At(pos, e) => write!(f, "{} at {}", e, pos),
+            ParseErr::InPath(path, e) => write!(f, "{} in {}", e, path),
+            ParseErr::InRecord(n, e) => write!(f, "{} in record {}", e, n),
+        }
+    }
+}
+
+impl std::error::Error for ParseErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.inner() {
+            ParseErr::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+
+/// The ways the input can be invalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+
+    /// Input ended part way through
+    UnexpectedEof,
+
+    /// A byte that can't go here, and a description of what could
+    UnexpectedByte { found: u8, expected: &'static str },
+
+    /// Unknown escape in a string, or a bad unicode escape
+    InvalidEscape,
+
+    /// A unicode escape for half of a surrogate pair, without the other half
+    UnpairedSurrogate,
+
+    /// A badly formed number, and the reason
+    InvalidNumber(&'static str),
+
+    /// Bytes that are not UTF-8
+    InvalidUtf8,
+
+    /// A control character (below 0x20) that should have been escaped
+    ControlCharacter(u8),
+
+    /// More input after the end of the value
+    TrailingData,
+
+    /// Arrays and objects are nested too deeply
+    DepthExceeded,
+
+    /// Some other limit on the input was exceeded, and what it was set to
+    LimitExceeded { limit: Limit, max: usize },
+}
+
+
+/// Limits that can be put on the input, other than depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    StringLength,
+    NumberLength,
+    ObjectMembers,
+    ArrayElements,
+    DocumentSize,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEof => write!(f, "Unexpected end of input"),
+            ErrorKind::UnexpectedByte { found, expected } if found.is_ascii_graphic() => 
+                write!(f, "Unexpected '{}', expected {}", *found as char, expected),
+            ErrorKind::UnexpectedByte { found, expected } => 
+                write!(f, "Unexpected byte 0x{:02x}, expected {}", found, expected),
+            ErrorKind::InvalidEscape => write!(f, "Invalid escape sequence"),
+            ErrorKind::UnpairedSurrogate => write!(f, "Unpaired surrogate in unicode escape"),
+            ErrorKind::InvalidNumber(reason) => write!(f, "Invalid number, {}", reason),
+            ErrorKind::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            ErrorKind::ControlCharacter(c) => write!(f, "Unescaped control character 0x{:02x} in string", c),
+            ErrorKind::TrailingData => write!(f, "Unexpected data after the end of the value"),
+            ErrorKind::DepthExceeded => write!(f, "Arrays and objects are nested too deeply"),
+            ErrorKind::LimitExceeded { limit, max } => match limit {
+                Limit::StringLength => write!(f, "String longer than the limit of {} bytes", max),
+                Limit::NumberLength => write!(f, "Number longer than the limit of {} chars", max),
+                Limit::ObjectMembers => write!(f, "Object has more than the limit of {} members", max),
+                Limit::ArrayElements => write!(f, "Array has more than the limit of {} elements", max),
+                Limit::DocumentSize => write!(f, "Input longer than the limit of {} bytes", max),
+            },
+        }
+    }
+}
+
+
+/// A position in the input.  Lines and columns count from 1, and
+/// columns count bytes (so a multi-byte UTF-8 char is more than one column)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// Bytes from the start of the input
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+
+    /// The position at the start of the input
+    pub fn start() -> Position {
+        Position { offset: 0, line: 1, column: 1 }
+    }
+
+    /// The position after the given bytes, starting from here
+    #[inline]
+    pub fn advance(self, bytes: &[u8]) -> Position {
+        let offset = self.offset + bytes.len();
+        // the count is quick (vectorised), only look for the last line if there is one
+        let lines = bytes.iter().filter(|b| **b == b'\n').count();
+        match lines {
+            0 => Position { offset, line: self.line, column: self.column + bytes.len() },
+            _ => {
+                let last = bytes.iter().rposition(|b| *b == b'\n').unwrap_or(0);
+                Position { offset, line: self.line + lines, column: bytes.len() - last }
+            }
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::start()
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
 }
 
 
@@ -76,7 +299,7 @@ pub enum ParseErr {
 pub type ParseResult<T> = Result<T, ParseErr>;
 
 
-/// Struct that creates a iterator of chars from a Read
+/// Struct that creates a iterator of chars from a Read, decoding UTF-8
 struct Chars<R: Read> {
     inner: Bytes<R>,
 }
@@ -89,29 +312,48 @@ impl <R: Read> Chars<R> {
         Chars{inner: b}
     }
 
+    /// Next byte of a multi-byte sequence, must be a continuation byte (10xxxxxx)
+    fn continuation(&mut self) -> ParseResult<u32> {
+        match self.inner.next() {
+            Some(Ok(b)) if b & 0xC0 == 0x80 => Ok((b & 0x3F) as u32),
+            Some(Ok(_)) => Err(ErrorKind::InvalidUtf8.into()),
+            Some(Err(e)) => Err(ParseErr::Io(e)),
+            None => Err(ErrorKind::InvalidUtf8.into()),
+        }
+    }
+
+    /// Decodes the rest of a character, given its first byte
+    fn decode(&mut self, b: u8) -> ParseResult<char> {
+        // the leading byte tells us how many bytes follow, and the smallest
+        // value that needs that many (anything less is "overlong")
+        let (mut c, extra, min) = match b {
+            0x00..=0x7F => return Ok(b as char),
+            0xC0..=0xDF => ((b & 0x1F) as u32, 1, 0x80),
+            0xE0..=0xEF => ((b & 0x0F) as u32, 2, 0x800),
+            0xF0..=0xF7 => ((b & 0x07) as u32, 3, 0x10000),
+            _ => return Err(ErrorKind::InvalidUtf8.into()),
+        };
+        for _i in 0..extra {
+            c = (c << 6) | self.continuation()?;
+        }
+        if c < min {
+            // overlong
+            return Err(ErrorKind::InvalidUtf8.into());
+        }
+        // None for surrogates and anything past 0x10FFFF
+        std::char::from_u32(c).ok_or_else(|| ErrorKind::InvalidUtf8.into())
+    }
+
 }
 
 impl <R: Read> Iterator for Chars<R> {
-    type Item = std::io::Result<char>;
+    type Item = ParseResult<char>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.inner.next() {
-                Some(r) => {
-                    if r.is_ok() {
-                        // convert -- worry about not ASCII later!!
-                        // what to do with full UTF-8 compliance ??
-
-                        let b = r.unwrap();
-                        if b < 128 { 
-                            return  Some(Ok(b as char))
-                        }
-                    } else {
-                        return Some(Err(r.unwrap_err()))
-                    }
-                }
-                None => return None,
-            }
+        match self.inner.next() {
+            Some(Ok(b)) => Some(self.decode(b)),
+            Some(Err(e)) => Some(Err(ParseErr::Io(e))),
+            None => None,
         }
     }
 }
@@ -124,11 +366,16 @@ pub enum Mark {
 
 
 /// A Re-windable stream of characters.
+/// Chars read are kept in the buffer as UTF-8, so positions are byte offsets
+/// that are always on a char boundary.
 pub struct RewindableChars<R:Read> {
 
     chars: Chars<R>,
     buffer: Vec<u8>, 
     pos: usize,
+
+    /// Position in the input of the start of `buffer`
+    start: Position,
 }
 
 
@@ -139,10 +386,16 @@ impl <R:Read> RewindableChars<R> {
         RewindableChars{
             chars: Chars::new(b),
             buffer: Vec::new(),
-            pos: 0
+            pos: 0,
+            start: Position::start(),
         }
     }
 
+    /// Position in the input of the next char
+    pub fn position(&self) -> Position {
+        self.start.advance(&self.buffer[..self.pos])
+    }
+
 
     /// Rewinds to the given mark
     pub fn rewind(&mut self, m: Mark) {
@@ -159,7 +412,10 @@ impl <R:Read> RewindableChars<R> {
 
     /// Accepts data read so far (forgets any current re-wind)
     pub fn accept(&mut self) -> Mark {
-        self.buffer.clear();
+        // anything we've re-wound over has not been read yet, so keep it
+        self.start = RewindableChars::position(self);
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
         Pos(0)
     }
 
@@ -167,6 +423,10 @@ impl <R:Read> RewindableChars<R> {
     pub fn backup(&mut self) {
         if self.pos != 0 {
             self.pos -= 1;
+            // step back over any continuation bytes to the start of the char
+            while self.pos != 0 && self.buffer[self.pos] & 0xC0 == 0x80 {
+                self.pos -= 1;
+            }
         }
     }
 
@@ -174,38 +434,36 @@ impl <R:Read> RewindableChars<R> {
 
 /// Implements an iterator for RewindableChars.
 impl <R: Read> Iterator for RewindableChars<R> {
-    type Item = std::io::Result<char>;
+    type Item = ParseResult<char>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // TODO: conversion of next char in the buffer for >127
 
         /* first we check our buffer and move the pointer along */
         if self.pos < self.buffer.len() {
-            self.pos += 1;
-            return Some(Ok(*self.buffer.get(self.pos-1).unwrap() as char)); 
+            let b = self.buffer[self.pos];
+            let len = match b {
+                0x00..=0x7F => { self.pos += 1; return Some(Ok(b as char)); },
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                _ => 4,
+            };
+            // only complete, valid chars are put in the buffer
+            let s = std::str::from_utf8(&self.buffer[self.pos..self.pos + len]).ok()?;
+            self.pos += len;
+            return s.chars().next().map(Ok);
         }
 
         match self.chars.next() {
-            Some(r) => {
-                if r.is_ok() {
-                    // convert -- worry about not ASCII later!!
-                    // what to do with full UTF-8 compliance
-                    let my_char = r.unwrap() as char;
-                    
-                    // Push the utf bytes onto the Vec
-                    if my_char as u32 > 127 {
-                        panic!("Need to sort out proper UTF-8 encoding!")
-                    } else {
-                        self.pos += 1;
-                        self.buffer.push(my_char as u8);
-                    }
-
-                    Some(Ok(my_char))
-                } else {
-                    // careful here on re-wrapping
-                    Some(Err(r.unwrap_err()))
-                }
+            Some(Ok(my_char)) => {
+                // Push the utf bytes onto the Vec
+                let mut utf8 = [0u8; 4];
+                let bytes = my_char.encode_utf8(&mut utf8).as_bytes();
+                self.buffer.extend_from_slice(bytes);
+                self.pos += bytes.len();
+                Some(Ok(my_char))
             }
+            // careful here on re-wrapping
+            Some(Err(e)) => Some(Err(e.at(RewindableChars::position(self)))),
             None => None,
         }
     }
@@ -254,6 +512,83 @@ pub mod tests {
         assert_eq!(" banana", s);
 
     }
+
+    #[test]
+    pub fn check_can_rewind_utf8() {
+        let mut rb = RewindableChars::new(Cursor::new("h\u{e9}llo \u{20ac}\u{1F600}!").bytes());
+        let mut s = String::new();
+
+        let m = rb.mark();
+        for _i in 0..5 {
+            s.push(rb.next().unwrap().unwrap());
+        }
+        assert_eq!("h\u{e9}llo", s);
+
+        // back up over the multi-byte chars one char at a time
+        rb.rewind(m);
+        rb.next();
+        rb.next();
+        rb.backup();
+        assert_eq!('\u{e9}', rb.next().unwrap().unwrap());
+
+        rb.accept();
+        s.clear();
+        for _i in 0..7 {
+            s.push(rb.next().unwrap().unwrap());
+        }
+        assert_eq!("llo \u{20ac}\u{1F600}!", s);
+        rb.backup();
+        rb.backup();
+        assert_eq!('\u{1F600}', rb.next().unwrap().unwrap());
+        assert!(rb.next().unwrap().is_ok());
+        assert!(rb.next().is_none());
+    }
+
+    #[test]
+    pub fn check_bad_utf8() {
+        let bad: [&[u8]; 7] = [
+            b"a\x80b",             // continuation with no start
+            b"a\xc3",              // ends part way through
+            b"a\xc3(",             // missing continuation
+            b"a\xc0\xaf",          // overlong
+            b"a\xed\xa0\x80",      // surrogate
+            b"a\xf4\x90\x80\x80",  // past 0x10FFFF
+            b"a\xff",
+        ];
+        for b in bad.iter() {
+            let mut rb = RewindableChars::new(Cursor::new(b).bytes());
+            assert_eq!('a', rb.next().unwrap().unwrap());
+            let e = rb.next().unwrap().unwrap_err();
+            assert!(matches!(e.kind(), Some(ErrorKind::InvalidUtf8)), "{:?}", b);
+            assert_eq!(Some(1), e.position().map(|p| p.offset));
+        }
+    }
+
+    #[test]
+    pub fn check_position() {
+        let mut rb = RewindableChars::new(Cursor::new("ab\n\u{e9}x\n\ny").bytes());
+        assert_eq!(Position::start(), rb.position());
+
+        let m = rb.mark();
+        for _i in 0..5 {
+            rb.next();
+        }
+        assert_eq!(Position { offset: 6, line: 2, column: 4 }, rb.position());
+
+        // rewinding goes back, accepting keeps the place
+        rb.rewind(m);
+        assert_eq!(Position::start(), rb.position());
+        for _i in 0..3 {
+            rb.next();
+        }
+        rb.accept();
+        assert_eq!(Position { offset: 3, line: 2, column: 1 }, rb.position());
+        for _i in 0..5 {
+            rb.next();
+        }
+        assert_eq!(Position { offset: 9, line: 4, column: 2 }, rb.position());
+        assert_eq!("line 4, column 2", rb.position().to_string());
+    }
 }
 
 #[macro_use]
@@ -266,6 +601,8 @@ pub mod parsers {
 
     #[cfg(test)]
     use std::io::{Cursor};
+    #[cfg(test)]
+    use super::ErrorKind;
 
 
     impl std::convert::From<std::io::Error> for ParseErr {
@@ -293,22 +630,14 @@ pub mod parsers {
         where R:Read 
     {
         // check the io_option, if None -- ithe we're EOF
-        if let Some(io_option) = rc.next() {
-            
-            if let Ok(y) = io_option {
-
-                if y != c {
-                    return Err(ParseErr::DidNotMatch);
-                } else {
-                    return Ok(())
-                }
-            } else {
-                return Err(ParseErr::DidNotMatch);
-            }
-        } else {
+        match rc.next() {
+            Some(Ok(y)) if y == c => Ok(()),
+            Some(Ok(_)) => Err(ParseErr::DidNotMatch),
+            // bad UTF-8 or I/O, can't carry on
+            Some(Err(e)) => Err(e),
             // unexpected oef
-            return Err(ParseErr::DidNotMatch);
-        }        
+            None => Err(ParseErr::DidNotMatch),
+        }
     }
 
 
@@ -363,15 +692,12 @@ pub mod parsers {
     {
 
         while let Some(x) = rc.next() {
-            if let Ok(y) = x {
-                if !f(y) {
-                    rc.backup();
-                    return Ok(());
-                } else {
-                    s.push(y);
-                }
+            let y = x?;
+            if !f(y) {
+                rc.backup();
+                return Ok(());
             } else {
-                // check for Eof, and ignore ?
+                s.push(y);
             }
         } 
         // TODO: What do with EOF here ?
@@ -384,13 +710,9 @@ pub mod parsers {
         where R: Read, F: Fn(char) -> bool 
     {
         while let Some(x) = rc.next() {
-            if let Ok(y) = x {
-                if !f(y) {
-                    rc.backup();
-                    return Ok(());
-                }
-            } else {
-                // check for Eof, and ignore ?
+            if !f(x?) {
+                rc.backup();
+                return Ok(());
             }
         } 
         // TODO: What do with EOF here ?
@@ -404,18 +726,17 @@ pub mod parsers {
         let mut count = 0usize;
         let mut result = String::with_capacity(n); // typically in UTF-8
         while let Some(x) = rc.next() {
-            if let Ok(y) = x {
-                if f(y) {
-                    result.push(y);
-                    count += 1;
-                    if count == n {
-                        return Ok(result);
-                    }
-                } else {
-                    // next char was not wanted .. backup
-                    rc.backup();
-                    return Err(ParseErr::DidNotMatch)
+            let y = x?;
+            if f(y) {
+                result.push(y);
+                count += 1;
+                if count == n {
+                    return Ok(result);
                 }
+            } else {
+                // next char was not wanted .. backup
+                rc.backup();
+                return Err(ParseErr::DidNotMatch)
             }
         }
 
@@ -448,8 +769,8 @@ pub mod parsers {
     pub fn check_match_str() -> ParseResult<()> {
         let mut rb = create_rc("apple banana cherry");
 
-        assert_eq!(true, match_str("apple", &mut rb)? );
-        assert_eq!(true, match_str(" banana", &mut rb)? );
+        assert!(match_str("apple", &mut rb)? );
+        assert!(match_str(" banana", &mut rb)? );
         assert!(match_str(" something else", &mut rb).is_err() );
         Ok(())
     }
@@ -458,8 +779,8 @@ pub mod parsers {
     pub fn check_match_str_eof() -> ParseResult<()> {
         let mut rb = create_rc("apple banana cherry");
 
-        assert_eq!(true, match_str("apple", &mut rb)? );
-        assert_eq!(true, match_str(" banana", &mut rb)? );
+        assert!(match_str("apple", &mut rb)? );
+        assert!(match_str(" banana", &mut rb)? );
 
         // this will run over the end, so will EOF .. how does it do that?
         // This is example of  to assert for error condition in a test
@@ -474,23 +795,50 @@ pub mod parsers {
         let mut rb = create_rc(" apple  banana\tcherry");
         
         skip_whitespace(&mut rb) ?;
-        assert_eq!(true, match_str("apple", &mut rb) ? );
+        assert!(match_str("apple", &mut rb) ? );
         skip_whitespace(&mut rb) ?;
-        assert_eq!(true, match_str("banana", &mut rb) ? );
+        assert!(match_str("banana", &mut rb) ? );
         skip_whitespace(&mut rb) ?;
-        assert_eq!(true, match_str("cherry", &mut rb) ? );
+        assert!(match_str("cherry", &mut rb) ? );
+        Ok(())
+    }
+
+
+
+    #[test]
+    pub fn check_non_ascii() -> ParseResult<()> {
+        let mut rb = create_rc("gr\u{fc}\u{df}e \u{65e5}\u{672c}\u{8a9e}\u{1F600} caf\u{e9}");
+
+        assert!(match_str("gr\u{fc}\u{df}e", &mut rb)?);
+        skip_whitespace(&mut rb)?;
+        let mut s = String::new();
+        capture_while(|c| !c.is_ascii(), &mut s, &mut rb)?;
+        assert_eq!("\u{65e5}\u{672c}\u{8a9e}\u{1F600}", s);
+        assert!(!match_str_optional("caff", &mut rb)?);
+        skip_whitespace(&mut rb)?;
+        assert_eq!("caf\u{e9}", capture_n(&mut rb, |c| c.is_alphabetic(), 4)?);
         Ok(())
     }
 
+    #[test]
+    pub fn check_bad_utf8_is_an_error() {
+        let mut rb = RewindableChars::new(Cursor::new(b"ab\xffcd").bytes());
+        let mut s = String::new();
+        let e = capture_while(|_| true, &mut s, &mut rb).unwrap_err();
+        assert!(matches!(e.kind(), Some(ErrorKind::InvalidUtf8)));
+        assert_eq!(Some(2), e.position().map(|p| p.offset));
 
+        let mut rb = RewindableChars::new(Cursor::new(b"ab\xffcd").bytes());
+        assert!(matches!(match_str("abc", &mut rb).unwrap_err().kind(), Some(ErrorKind::InvalidUtf8)));
+    }
 
     #[test]
     pub fn check_capture_n() {
 
         let mut rc = create_rc("AAAAAAAxxxxxxx");
 
-        let match_a = |c| return c == 'A';
-        let match_x = |c| return c == 'x';
+        let match_a = |c| c == 'A';
+        let match_x = |c| c == 'x';
 
         match capture_n(&mut rc, match_a, 7) {
             Ok(s) => assert_eq!("AAAAAAA", s), 
@@ -508,8 +856,8 @@ pub mod parsers {
     pub fn check_match_all_macro() {
         let mut rc = create_rc("AAAAAAAxxxxxxxA");
 
-        let match_a = |c:char| return c == 'A';
-        let match_x = |c:char| return c == 'x';
+        let match_a = |c:char| c == 'A';
+        let match_x = |c:char| c == 'x';
 
         // match_or!(&mut rc, capture_n(&mut rc, match_a, 7)  ) ;
         let mut x = match_all!(&mut rc
@@ -533,13 +881,7 @@ pub mod parsers {
             , capture_n(&mut rc, match_a, 1)  
         ) ;
 
-        assert!(
-            if let Err(ParseErr::DidNotMatch) = x {
-                true
-            } else {
-                false
-            }
-        );
+        assert!(matches!(x, Err(ParseErr::DidNotMatch)));
 
     }
 
@@ -557,13 +899,7 @@ pub mod parsers {
         ) ;
         
         // So cannot "return" from my macro == need to do something else!
-        assert!(
-            if let Err(ParseErr::DidNotMatch) = x {
-                true
-            } else {
-                false
-            }
-        );
+        assert!(matches!(x, Err(ParseErr::DidNotMatch)));
 
         x = match_or!(&mut rc, 
             match_char('x', &mut rc),
@@ -582,11 +918,9 @@ pub mod parsers {
         
         /// alpha char
         fn p_alpha<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<char> {
-            if let Some(x) = rc.next() {
-                if let Ok(c) = x {
-                    if (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') {
-                        return Ok(c);
-                    }
+            if let Some(Ok(c)) = rc.next() {
+                if c.is_ascii_alphabetic() {
+                    return Ok(c);
                 }
             }
             Err(ParseErr::DidNotMatch)
@@ -599,8 +933,8 @@ pub mod parsers {
             let mut s = String::new();
             s.push ( p_alpha(rc) ? );
 
-            let fn_alphanum = |c| {
-                (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9')
+            let fn_alphanum = |c: char| {
+                c.is_ascii_alphanumeric()
             };
             parsers::capture_while(fn_alphanum, &mut s, rc) ?;
             println!("{} matched {}", line!(), s);
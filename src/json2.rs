@@ -1,22 +1,31 @@
 //! Json parser, using only an iterator over bytes
 
-use std::{io::Read};
+use std::io::{self, Read};
+use std::borrow::Cow;
+use std::fmt;
 use std::vec::Vec;
-use crate::internals::{ParseResult, ParseErr};
-
-const U8_START_OBJ:u8 = '{' as u8;
-const U8_START_ARR:u8 = '[' as u8;
-const U8_QUOTE:u8 = '\"' as u8;
-const U8_ESCAPE:u8 = '\\' as u8;
-const U8_MINUS:u8 = '-' as u8;
-const U8_0:u8 = '0' as u8;
-const U8_9:u8 = '9' as u8;
-const U8_PERIOD:u8 = '.' as u8;
+use crate::internals::{ParseResult, ParseErr, ErrorKind, Limit, Position};
+use crate::json_number::JsonNumber;
+
+const U8_START_OBJ:u8 = b'{';
+const U8_START_ARR:u8 = b'[';
+const U8_QUOTE:u8 = b'"';
+const U8_ESCAPE:u8 = b'\\';
+const U8_MINUS:u8 = b'-';
+const U8_0:u8 = b'0';
+const U8_9:u8 = b'9';
+const U8_PERIOD:u8 = b'.';
+const U8_PLUS:u8 = b'+';
+const U8_EXP:u8 = b'e';
+const U8_EXP_UPPER:u8 = b'E';
 
 
 // Character flags
 // we have 8 to choose from
 //  - non-plain text chars like " and \ when looking at strings
+//  - what matters when skipping over values, outside and inside strings
+const FLAG_IN_STRING:u8 = 16;
+const FLAG_STRUCT:u8 = 8;
 const FLAG_WS:u8 = 4;
 const FLAG_DIGIT:u8 = 2;
 const FLAG_NOT_TEXT:u8 = 1;
@@ -43,9 +52,25 @@ const CHAR_FLAGS : [u8; 256] = {
     x[b'8' as usize] = FLAG_DIGIT;
     x[b'9' as usize] = FLAG_DIGIT;
 
-    // Not plain text
-    x[b'\\' as usize] = FLAG_NOT_TEXT;
-    x[b'"' as usize] = FLAG_NOT_TEXT;
+    // Not plain text, includes control chars which must be escaped and 
+    // anything outside ASCII so we know when we need to check UTF-8
+    x[b'\\' as usize] = FLAG_NOT_TEXT | FLAG_IN_STRING;
+    x[b'"' as usize] = FLAG_NOT_TEXT | FLAG_IN_STRING | FLAG_STRUCT;
+
+    // Brackets and quotes, all that's needed to find the end of an array or object
+    x[b'[' as usize] = FLAG_STRUCT;
+    x[b']' as usize] = FLAG_STRUCT;
+    x[b'{' as usize] = FLAG_STRUCT;
+    x[b'}' as usize] = FLAG_STRUCT;
+    // and newlines, so lines are still counted
+    x[b'\n' as usize] |= FLAG_STRUCT | FLAG_IN_STRING;
+    let mut i = 0;
+    while i < 256 {
+        if i < 0x20 || i >= 0x80 {
+            x[i] |= FLAG_NOT_TEXT;
+        }
+        i += 1;
+    }
 
     x
 };
@@ -71,25 +96,30 @@ const fn is_not_text(c: u8) -> bool {
 
 /// Checks a sequence of bytes match - useful for constants
 macro_rules! byte_seq {
-    // first arg is parser/rewinder, then the args
-    ($s:expr, $( $x:expr ),* ) => (
+    // first arg is parser/rewinder, then what is expected for errors, then the args
+    ($s:expr, $expected:expr, $( $x:expr ),* ) => (
         { 
             $(
-                    $s.match_char($x as u8)?;
+                    $s.match_char($x, $expected)?;
             )* 
         }
     );
 }
 
 /// What does life time mean?
+/// The text of strings and member names is `S`, borrowed from the parser unless the
+/// events come from `JsonParser::next_borrowed`
 #[derive(Debug)]
-pub enum JsonEvent2<'a> {
+pub enum JsonEvent2<'a, S = &'a str> {
 
     /// A string value
-    String(&'a str),
+    String(S),
+
+    /// The name of an object member, emitted before the member's value
+    PropertyName(S),
 
     /// Event though it is a number, we'll leave to the client to decide what to co-erce it into (int, float or other)
-    Number(&'a str),
+    Number(JsonNumber<'a>),
 
     /// Bool is true or false
     Boolean(bool),
@@ -106,111 +136,533 @@ pub enum JsonEvent2<'a> {
 
 
 /// These functions are mainly used to support test cases
-impl <'a> JsonEvent2<'a> {
+impl <'a, S> JsonEvent2<'a, S> {
 
     pub fn is_string(&self) -> bool {
-        match self {
-            &JsonEvent2::String(_) => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::String(_))
+    }
+    pub fn is_property_name(&self) -> bool {
+        matches!(self, JsonEvent2::PropertyName(_))
     }
     pub fn is_number(&self) -> bool {
+        matches!(self, JsonEvent2::Number(_))
+    }
+    /// The number, if this is a number event
+    pub fn as_number(&self) -> Option<&JsonNumber<'a>> {
         match self {
-            &JsonEvent2::Number(_) => true,
-            _ => false
+            JsonEvent2::Number(n) => Some(n),
+            _ => None
         }
     }
     pub fn is_null(&self) -> bool {
-        match self {
-            &JsonEvent2::Null => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::Null)
     }
     pub fn is_bool(&self) -> bool {
-        match self {
-            &JsonEvent2::Boolean(_) => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::Boolean(_))
     }
     pub fn is_obj_start(&self) -> bool {
-        match self {
-            &JsonEvent2::ObjectStart  => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::ObjectStart)
     }
     pub fn is_obj_end(&self) -> bool {
-        match self {
-            &JsonEvent2::ObjectEnd => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::ObjectEnd)
     }
     pub fn is_arr_start(&self) -> bool {
-        match self {
-            &JsonEvent2::ArrayStart => true,
-            _ => false
-        }
+        matches!(self, JsonEvent2::ArrayStart)
     }
     pub fn is_arr_end(&self) -> bool {
-        match self {
-            &JsonEvent2::ArrayEnd => true,
-            _ => false
+        matches!(self, JsonEvent2::ArrayEnd)
+    }
+
+}
+
+
+/// Limits on the input, for when it can't be trusted.  Going over one is an error
+/// saying which.  By default there are no limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonParserConfig {
+    /// How deeply arrays and objects can be nested
+    pub max_depth: usize,
+
+    /// Longest string or property name, in bytes once escapes are decoded
+    pub max_string_len: usize,
+
+    /// Longest number, in chars
+    pub max_number_len: usize,
+
+    /// Most members in one object
+    pub max_object_members: usize,
+
+    /// Most elements in one array
+    pub max_array_elements: usize,
+
+    /// Most bytes of input
+    pub max_document_size: usize,
+}
+
+impl Default for JsonParserConfig {
+    fn default() -> Self {
+        JsonParserConfig {
+            max_depth: usize::MAX,
+            max_string_len: usize::MAX,
+            max_number_len: usize::MAX,
+            max_object_members: usize::MAX,
+            max_array_elements: usize::MAX,
+            max_document_size: usize::MAX,
+        }
+    }
+}
+
+
+/// Sets up a `JsonParser`, with sizes to tune it for the input and all the options in one place.
+/// Set what's needed, then `build` with the input
+#[derive(Debug, Clone)]
+pub struct JsonParserBuilder {
+    buffer_size: usize,
+    string_capacity: usize,
+    stack_capacity: usize,
+    multiple_values: bool,
+    config: JsonParserConfig,
+}
+
+impl Default for JsonParserBuilder {
+    fn default() -> Self {
+        JsonParserBuilder {
+            buffer_size: 64 * 1024,
+            string_capacity: 300, // guess at effective initial size
+            stack_capacity: 10, // 10 deep reasonable default
+            multiple_values: false,
+            config: JsonParserConfig::default(),
+        }
+    }
+}
+
+impl JsonParserBuilder {
+
+    pub fn new() -> JsonParserBuilder {
+        Self::default()
+    }
+
+    /// How much input is read at a time (at least one byte).  There's no need to
+    /// wrap the input in a `BufReader` as well
+    pub fn buffer_size(mut self, size: usize) -> JsonParserBuilder {
+        self.buffer_size = size.max(1);
+        self
+    }
+
+    /// Starting size of the buffer for strings that need copying, which is those with
+    /// escapes or that cross a re-fill of the buffer.  It grows as needed
+    pub fn string_capacity(mut self, capacity: usize) -> JsonParserBuilder {
+        self.string_capacity = capacity;
+        self
+    }
+
+    /// Starting size of the stack of arrays and objects we are in.  It grows as needed
+    pub fn stack_capacity(mut self, capacity: usize) -> JsonParserBuilder {
+        self.stack_capacity = capacity;
+        self
+    }
+
+    /// Allows a stream of top level values, one after another (say `1 2 [3]`), rather than
+    /// just one.  Otherwise anything after the first value is an error
+    pub fn multiple_values(mut self, allow: bool) -> JsonParserBuilder {
+        self.multiple_values = allow;
+        self
+    }
+
+    /// All the limits at once
+    pub fn config(mut self, config: JsonParserConfig) -> JsonParserBuilder {
+        self.config = config;
+        self
+    }
+
+    /// Limits how deeply arrays and objects can be nested, going deeper is an error.
+    /// There is no limit unless set, set one when the input can't be trusted
+    pub fn max_depth(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_depth = max;
+        self
+    }
+
+    pub fn max_string_len(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_string_len = max;
+        self
+    }
+
+    pub fn max_number_len(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_number_len = max;
+        self
+    }
+
+    pub fn max_object_members(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_object_members = max;
+        self
+    }
+
+    pub fn max_array_elements(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_array_elements = max;
+        self
+    }
+
+    pub fn max_document_size(mut self, max: usize) -> JsonParserBuilder {
+        self.config.max_document_size = max;
+        self
+    }
+
+    /// Parser for the input, which is either any `Read` or a `SliceInput`
+    pub fn build<R: JsonInput>(self, r: R) -> JsonParser<R> {
+        JsonParser {
+            read: r,
+            // sized on the first read, so never allocated for a slice
+            buffer : Vec::new(),
+            buf_size: self.buffer_size,
+            buf_pos: 0,
+            buf_offset: 0,
+            string_buff : Vec::with_capacity(self.string_capacity),
+            tok_start: None,
+            tok_spilled: false,
+            tok_text: (0, 0),
+            tok_offset: 0,
+            line: 1,
+            line_start: 0,
+            stack: Vec::with_capacity(self.stack_capacity),
+            names: String::new(),
+            after_name: false,
+            root_done: false,
+            multiple_values: self.multiple_values,
+            line_delimited: false,
+            too_long: false,
+            string_mark: None,
+            resume: None,
+            config: self.config,
+        }
+    }
+}
+
+
+/// Where a `JsonParser` gets its bytes.  Any `Read` is read into the parser's own buffer,
+/// a piece at a time, while a `SliceInput` is scanned where it is with no copying
+pub trait JsonInput {
+    /// The bytes to scan now, given the parser's buffer
+    fn bytes<'b>(&'b self, buffer: &'b [u8]) -> &'b [u8];
+
+    /// Moves on to the next bytes of input, reading up to `size` of them into `buffer`
+    /// if needed.  No bytes left means the end of the input
+    fn refill(&mut self, buffer: &mut Vec<u8>, size: usize) -> io::Result<()>;
+
+    /// Drops all but the first `len` bytes to scan, those past a limit
+    fn truncate(&mut self, buffer: &mut Vec<u8>, len: usize) {
+        buffer.truncate(len);
+    }
+}
+
+impl <R: Read> JsonInput for R {
+    #[inline]
+    fn bytes<'b>(&'b self, buffer: &'b [u8]) -> &'b [u8] {
+        buffer
+    }
+
+    fn refill(&mut self, buffer: &mut Vec<u8>, size: usize) -> io::Result<()> {
+        buffer.resize(size, 0);
+        let n = self.read(buffer)?;
+        buffer.truncate(n);
+        Ok(())
+    }
+}
+
+
+/// Input that is all in memory already, see `JsonParser::from_slice`
+#[derive(Debug, Clone)]
+pub struct SliceInput<'a> {
+    /// All of the input
+    input: &'a [u8],
+    /// The bytes being scanned, empty until the first refill and after the last
+    current: &'a [u8],
+    /// Bytes not yet scanned, all of them until the first refill
+    rest: &'a [u8],
+}
+
+impl <'a> SliceInput<'a> {
+    pub fn new(bytes: &'a [u8]) -> SliceInput<'a> {
+        SliceInput { input: bytes, current: &[], rest: bytes }
+    }
+}
+
+impl <'a> JsonInput for SliceInput<'a> {
+    #[inline]
+    fn bytes<'b>(&'b self, _buffer: &'b [u8]) -> &'b [u8] {
+        self.current
+    }
+
+    fn refill(&mut self, _buffer: &mut Vec<u8>, _size: usize) -> io::Result<()> {
+        // the whole slice at once, then nothing
+        self.current = std::mem::take(&mut self.rest);
+        Ok(())
+    }
+
+    fn truncate(&mut self, _buffer: &mut Vec<u8>, len: usize) {
+        self.current = &self.current[..len.min(self.current.len())];
+    }
+}
+
+
+/// Input given to the parser a piece at a time, as it arrives.  Running out part way
+/// through a token isn't the end of the input, so the parser can go back and try the
+/// token again once there is more
+#[derive(Debug, Default)]
+pub(crate) struct FeedInput {
+    /// Bytes given so far, less those dropped once parsed
+    data: Vec<u8>,
+    /// No more bytes will be given
+    ended: bool,
+}
+
+impl JsonInput for FeedInput {
+    #[inline]
+    fn bytes<'b>(&'b self, _buffer: &'b [u8]) -> &'b [u8] {
+        &self.data
+    }
+
+    fn refill(&mut self, _buffer: &mut Vec<u8>, _size: usize) -> io::Result<()> {
+        if !self.ended {
+            // the parser puts itself back to the start of the token, see `next_fed`
+            return Err(io::ErrorKind::WouldBlock.into());
         }
+        self.data.clear();
+        Ok(())
+    }
+
+    fn truncate(&mut self, _buffer: &mut Vec<u8>, len: usize) {
+        self.data.truncate(len);
     }
+}
 
+
+/// Private version of `JsonEvent2`, any text is left in the parser until the
+/// event is handed out
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Token {
+    String,
+    PropertyName,
+    /// Number with the end of its integer and fraction parts
+    Number(usize, usize),
+    Boolean(bool),
+    Null,
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
 }
 
 
 /// Private enum that keeps track of parse position
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum JsonStackItem {
     /// Where vaue is number of elements in array discovered during the parse
     Array(usize),
-    /// WHere value is number of named members in the JSON object discovered so far
-    Object(usize)
+    /// WHere value is number of named members in the JSON object discovered so far,
+    /// and where the current member's name is
+    Object(usize, NameAt)
+}
+
+
+/// Where the name of an object's current member is.  Names are left in the buffer, and
+/// only copied to `names` when the buffer is about to move on, so most never are
+#[derive(Debug, Clone, Copy)]
+enum NameAt {
+    Buffer(usize, usize),
+    Names(usize, usize),
+}
+
+
+/// A step in the path from the top of the document to a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// Member of an object
+    Name(&'a str),
+    /// Element of an array, from 0
+    Index(usize),
+}
+
+
+/// Written as in a JSONPath, `.name`, `['odd name']` or `[3]`, so the segments of a
+/// path following `$` make the whole path
+impl fmt::Display for PathSegment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+            PathSegment::Name(name) => {
+                let plain = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                match plain {
+                    true => write!(f, ".{}", name),
+                    false => write!(f, "['{}']", name.replace('\\', "\\\\").replace('\'', "\\'")),
+                }
+            },
+        }
+    }
 }
 
-pub struct JsonParser<R: Read> {
+pub struct JsonParser<R: JsonInput> {
 
     /// Peekable means we can "look ahed" in the iteration
     // bytes: Bytes<R>,
     read: R,
 
-    /// Local buffer seems faster than Reader.bytes() / Bytes
+    /// Local buffer seems faster than Reader.bytes() / Bytes.  Not used when
+    /// the input is a slice, see `buf` for the bytes being scanned
     buffer: Vec<u8>,
 
+    /// How much is read into `buffer` at a time
+    buf_size: usize,
+
     // buffer position and capacty info
     buf_pos: usize,
+
+    /// Offset in the input of the start of `buffer`
+    buf_offset: usize,
     //buf_cap: usize,
 
-    /// A buffer for collecting the current value being parsed
-    string_buff: String,
+    /// A buffer for collecting the current value being parsed, only used
+    /// when the value straddles a re-fill of `buffer`
+    string_buff: Vec<u8>,
+
+    /// Start of the token being scanned in `buffer`, if any
+    tok_start: Option<usize>,
+
+    /// The start of the current token has been moved into `string_buff`
+    tok_spilled: bool,
+
+    /// The text of the last token, in `buffer` unless it was spilled
+    tok_text: (usize, usize),
+
+    /// Offset in the input of the start of the last token
+    tok_offset: usize,
+
+    /// Line of the input we are on, counted as newlines are skipped
+    line: usize,
+
+    /// Offset in the input of the start of that line
+    line_start: usize,
 
     /// For iterative parsing, we keep items on the 
     /// stack 
-    stack: Vec<JsonStackItem>
+    stack: Vec<JsonStackItem>,
+
+    /// Names of current members that were copied out of the buffer, outermost first
+    names: String,
+
+    /// Set once a member name has been returned, the member's value is next
+    after_name: bool,
+
+    /// A whole value has been parsed at the top level
+    root_done: bool,
+
+    /// More than one top level value is allowed, one after another
+    multiple_values: bool,
+
+    /// Each value is on a line of its own, so whitespace stops at a newline
+    /// and a value can't carry on past one
+    line_delimited: bool,
+
+    /// The input went over `max_document_size`, which is an error from then on
+    too_long: bool,
+
+    /// How far the string being matched has got, each time it might run out of input:
+    /// the offset of its opening quote, the offset it's checked up to and the length
+    /// of its text so far
+    string_mark: Option<(usize, usize, usize)>,
+
+    /// A string the input ran out in, to carry on with rather than scan again, see `next_fed`
+    resume: Option<StringResume>,
+
+    /// Limits on the input
+    config: JsonParserConfig,
 
 }
 
 
-impl <R: Read> JsonParser<R> {
+impl <'a> JsonParser<SliceInput<'a>> {
 
-    pub fn new(r: R) -> JsonParser<R> {
-        JsonParser {
-            read: r,
-            buffer : Vec::with_capacity(32 * 1024),
-            buf_pos: 0,
-            string_buff : String::with_capacity(300), // guess at effective initial size
-            stack: Vec::with_capacity(10), // 10 deep reasonable default
+    /// Parser for a document that is all in memory.  The input is scanned where it
+    /// is rather than read into a buffer, so strings and numbers are borrowed straight
+    /// from it, unless they have escapes to decode.  `next_borrowed` gives events that
+    /// can be kept after the next one.  Use `JsonParserBuilder::build` with a `SliceInput`
+    /// to set any options
+    pub fn from_slice(bytes: &'a [u8]) -> JsonParser<SliceInput<'a>> {
+        JsonParserBuilder::new().build(SliceInput::new(bytes))
+    }
+
+    /// Next event, which can outlive the parser as its text is borrowed from the input.
+    /// Only strings and member names with escapes are copied, to decode them
+    pub fn next_borrowed(&mut self) -> ParseResult<Option<JsonEvent2<'a, Cow<'a, str>>>> {
+        let t = match self.next_located()? {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let text = || match self.borrowed_text() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(String::from(self.token_text())),
+        };
+        Ok(Some(match t {
+            Token::String => JsonEvent2::String(text()),
+            Token::PropertyName => JsonEvent2::PropertyName(text()),
+            Token::Number(int_end, frac_end) => {
+                // no escapes, so it's as it is in the input, even if it was moved
+                // into `string_buff` on reaching the end
+                let len = self.token_text().len();
+                let text = &self.read.input[self.tok_offset..self.tok_offset + len];
+                JsonEvent2::Number(JsonNumber::from_parts(unsafe { std::str::from_utf8_unchecked(text) }, int_end, frac_end))
+            },
+            Token::Boolean(b) => JsonEvent2::Boolean(b),
+            Token::Null => JsonEvent2::Null,
+            Token::ObjectStart => JsonEvent2::ObjectStart,
+            Token::ObjectEnd => JsonEvent2::ObjectEnd,
+            Token::ArrayStart => JsonEvent2::ArrayStart,
+            Token::ArrayEnd => JsonEvent2::ArrayEnd,
+        }))
+    }
+
+    /// The text of the last token as it is in the input, unless it had escapes
+    /// to decode
+    pub(crate) fn borrowed_text(&self) -> Option<&'a str> {
+        match self.tok_spilled {
+            true => None,
+            // strings are checked as they are matched, numbers are only ever ASCII
+            false => Some(unsafe { std::str::from_utf8_unchecked(&self.read.current[self.tok_text.0..self.tok_text.1]) }),
         }
     }
+}
+
+
+impl <R: JsonInput> JsonParser<R> {
+
+    pub fn new(r: R) -> JsonParser<R> {
+        JsonParserBuilder::new().build(r)
+    }
+
+    /// The bytes being scanned, from `buffer` or straight from the input
+    #[inline]
+    fn buf(&self) -> &[u8] {
+        self.read.bytes(&self.buffer)
+    }
+
+    /// One value per line, as for JSON lines
+    pub(crate) fn line_delimited(mut self) -> JsonParser<R> {
+        self.multiple_values = true;
+        self.line_delimited = true;
+        self
+    }
+
+    pub fn config(&self) -> &JsonParserConfig {
+        &self.config
+    }
 
     /// "Peek" the next byte - used if we want to check if the next token
     /// is equal to something, and only consume it if is.  (Say we want ot check for  keyword etc})
     #[inline]
     fn peek(&mut self) -> ParseResult<Option<u8>> {
         self.ensure_buffer()?;
-        if self.buf_pos < self.buffer.len() {
-            Ok(Some(self.buffer[self.buf_pos]))
+        if self.buf_pos < self.buf().len() {
+            Ok(Some(self.buf()[self.buf_pos]))
         } else {
             Ok(None)
         }
@@ -218,7 +670,7 @@ impl <R: Read> JsonParser<R> {
 
     #[inline]
     fn ensure_buffer(&mut self) -> ParseResult<()> {
-        if self.buf_pos >= self.buffer.len() {
+        if self.buf_pos >= self.buf().len() {
             self.replace_buffer()? ;
         }
         Ok(())
@@ -228,7 +680,7 @@ impl <R: Read> JsonParser<R> {
     #[inline]
     fn consume_if(&mut self, b: u8) -> ParseResult<bool> {
         self.ensure_buffer()?;
-        if self.buf_pos < self.buffer.len() &&  b == self.buffer[self.buf_pos] {
+        if self.buf_pos < self.buf().len() &&  b == self.buf()[self.buf_pos] {
             self.buf_pos += 1;
             Ok(true)
         } else {
@@ -237,13 +689,64 @@ impl <R: Read> JsonParser<R> {
     }
 
 
+    /// Position in the input of the next byte to be parsed
+    pub fn position(&self) -> Position {
+        self.position_of(self.buf_offset + self.buf_pos)
+    }
+
+    /// Position in the input of the start of the last token returned
+    pub fn token_position(&self) -> Position {
+        // a token never spans lines, so we are still on its line
+        self.position_of(self.tok_offset)
+    }
+
+    /// Position of an offset on the current line
+    fn position_of(&self, offset: usize) -> Position {
+        Position { offset, line: self.line, column: offset - self.line_start + 1 }
+    }
+
+    /// Counts the newline at `pos` in the buffer, which has been skipped
+    #[inline]
+    fn new_line(&mut self, pos: usize) {
+        self.line += 1;
+        self.line_start = self.buf_offset + pos + 1;
+    }
+
+    /// Marks the current position as the start of the next token returned
+    #[inline]
+    fn mark_token(&mut self) {
+        self.tok_offset = self.buf_offset + self.buf_pos;
+    }
+
+    #[cold]
     fn replace_buffer(&mut self) -> ParseResult<()> {
+        let max = self.config.max_document_size;
+        if self.too_long {
+            return Err(Self::limit_exceeded(Limit::DocumentSize, max));
+        }
+
+        // a token in progress would be lost, so keep what we have of it
+        self.keep_names();
+        if self.tok_start.is_some() {
+            self.spill_token(self.buf_pos);
+            self.tok_start = Some(0);
+        }
+
         // re-fill the buffer
-        self.buffer.resize(8*0124, 0);
+        self.buf_offset += self.buf().len();
         self.buf_pos = 0;
-        match self.read.read(&mut self.buffer) {
-            Ok(n) => { 
-                self.buffer.truncate(n);
+        match self.read.refill(&mut self.buffer, self.buf_size) {
+            Ok(()) => { 
+                let n = self.buf().len();
+                if self.buf_offset + n > max {
+                    // nothing past the limit is parsed, however often we are asked,
+                    // and errors point at the first byte over
+                    let keep = max.saturating_sub(self.buf_offset);
+                    self.read.truncate(&mut self.buffer, keep);
+                    self.buf_pos = keep;
+                    self.too_long = true;
+                    return Err(Self::limit_exceeded(Limit::DocumentSize, max));
+                }
                 Ok(())
             } ,
             Err(io) =>  Err(ParseErr::Io(io)),
@@ -257,39 +760,121 @@ impl <R: Read> JsonParser<R> {
 
         loop {
 
-            if self.buf_pos < self.buffer.len() {
-                let x = self.buffer[self.buf_pos] ;
+            // scan with locals, as going through `self` reloads the buffer for every byte
+            let buf = self.read.bytes(&self.buffer);
+            let mut pos = self.buf_pos;
+            while pos < buf.len() {
+                let x = buf[pos];
                 //if x == 32 || x==9 || x == 8 || x == 10 || x == 13 {
-                if is_whitespace(x) {
-                    self.buf_pos += 1 ;
-                } else {
+                if !is_whitespace(x) {
+                    self.buf_pos = pos;
                     return Ok(());
                 }
-            } else {
-                self.replace_buffer()?;
-                // check for EOF
-                if self.buffer.len() == 0 {
-                    return Ok(());
+                if x == b'\n' {
+                    if self.line_delimited {
+                        self.buf_pos = pos;
+                        return Ok(());
+                    }
+                    self.line += 1;
+                    self.line_start = self.buf_offset + pos + 1;
                 }
+                pos += 1;
+            }
+            self.buf_pos = pos;
+            self.replace_buffer()?;
+            // check for EOF
+            if self.buf().is_empty() {
+                return Ok(());
             }
         }
 
     }
 
+    /// Marks the current position as the start of a token, so
+    /// its bytes survive a re-fill of the buffer
+    #[inline]
+    fn start_token(&mut self) {
+        self.tok_start = Some(self.buf_pos);
+        self.tok_spilled = false;
+    }
+
+    /// Copies the token so far, up to `end`, into `string_buff`
+    #[inline(never)]
+    fn spill_token(&mut self, end: usize) {
+        if !self.tok_spilled {
+            self.string_buff.clear();
+            self.tok_spilled = true;
+        }
+        if let Some(start) = self.tok_start {
+            self.string_buff.extend_from_slice(&self.read.bytes(&self.buffer)[start..end]);
+        }
+    }
+
+    /// Ends the token started by `start_token` at `end`, its text is then
+    /// available from `token_text`
+    #[inline]
+    fn end_token(&mut self, end: usize) {
+        let start = self.tok_start.take().unwrap_or(end);
+        if self.tok_spilled {
+            self.string_buff.extend_from_slice(&self.read.bytes(&self.buffer)[start..end]);
+        }
+        self.tok_text = (start, end);
+    }
+
+    /// Length so far of the token in progress, if it ended at `end`
+    #[inline]
+    fn token_len(&self, end: usize) -> usize {
+        let spilled = if self.tok_spilled { self.string_buff.len() } else { 0 };
+        spilled + end - self.tok_start.unwrap_or(end)
+    }
+
+    /// Error for going over one of the limits in the config
+    #[cold]
+    fn limit_exceeded(limit: Limit, max: usize) -> ParseErr {
+        ErrorKind::LimitExceeded { limit, max }.into()
+    }
+
+    /// Error for a token longer than the limit, at the start of the token
+    #[cold]
+    fn token_too_long(&mut self, limit: Limit, max: usize) -> ParseErr {
+        Self::limit_exceeded(limit, max).at(self.token_position())
+    }
+
+    /// The text of the last token.  This is borrowed from `buffer` unless 
+    /// the token straddled a re-fill
+    #[inline]
+    pub(crate) fn token_text(&self) -> &str {
+        let text = if self.tok_spilled {
+            &self.string_buff
+        } else {
+            &self.buf()[self.tok_text.0..self.tok_text.1]
+        };
+        // strings are checked as they are matched, numbers are only ever ASCII
+        unsafe { std::str::from_utf8_unchecked(text) }
+    }
+
     /// Don't inline it -- check it makes go any faster!
     #[inline]
-    fn match_char(&mut self, c: u8) -> ParseResult<()> {
+    fn match_char(&mut self, c: u8, expected: &'static str) -> ParseResult<()> {
 
         // No, what if we need to get the next char?
         //self.buf_pos += 1;
         self.ensure_buffer()?;
-        if self.buf_pos < self.buffer.len() {
-            if self.buffer[self.buf_pos]  == c {
-                self.buf_pos += 1;
-                return Ok(());
-            }
+        if self.buf_pos < self.buf().len() && self.buf()[self.buf_pos] == c {
+            self.buf_pos += 1;
+            return Ok(());
+        }
+        Err(self.unexpected(expected))
+    }
+
+    /// Error for the byte at the current position (or the end of input) when
+    /// something else was expected.  The buffer must have been filled already
+    #[cold]
+    fn unexpected(&self, expected: &'static str) -> ParseErr {
+        match self.buf().get(self.buf_pos) {
+            Some(b) => ErrorKind::UnexpectedByte { found: *b, expected }.into(),
+            None => ErrorKind::UnexpectedEof.into(),
         }
-        Err(ParseErr::DidNotMatch)
     }
 
     /// Used on the remainder of the SIMD func, we put here
@@ -302,9 +887,9 @@ impl <R: Read> JsonParser<R> {
         )
     }
 
-    /// Attempt at SIMD (sse2) for matching digits 
+    /// Attempt at SIMD (sse2) for matching digits, returns how many digits were matched
     #[inline]
-    fn match_digits_simd(&mut self) -> ParseResult<bool> {
+    fn match_digits_simd(&mut self) -> ParseResult<usize> {
         //Compare packed unsigned 8-bit integers in a and b based 
         // on the comparison operand specified by imm8, and store the results
         // in mask vector k.
@@ -317,11 +902,13 @@ impl <R: Read> JsonParser<R> {
         let zeros = unsafe {_mm_set1_epi8(b'0' as i8) };
         let nines = unsafe { _mm_set1_epi8(b'9' as i8) };
 
+        let mut total = 0usize;
+
         // edge-case --ensure we have room in buffer, not starting on empty!
-        while !self.buffer.is_empty()  {
-            //assert!(self.buf_cap <= self.buffer.len()); // to remove bounds checking
+        while !self.buf().is_empty()  {
+            //assert!(self.buf_cap <= self.buf().len()); // to remove bounds checking
 
-            let c = self.buffer[self.buf_pos..self.buffer.len()].chunks_exact(16);
+            let c = self.buf()[self.buf_pos..self.buf().len()].chunks_exact(16);
             let remainder = c.remainder();
 
             let mut num_digits = 0usize;
@@ -338,10 +925,10 @@ impl <R: Read> JsonParser<R> {
                     let x1 = _mm_movemask_epi8(x);
                     let y1 = _mm_movemask_epi8(y);
 
-                    // rather than take the min, AND with 31 (as we know answer will be in lower bits)
-                    // the two results x1,y1 are both < 16, 
-                    // answer from _mm_tzcnt_32 can be 32 if all matched (as it takes u32 rather than u16)
-                    0b11111 & _mm_tzcnt_32( (x1 | y1 ) as u32) as usize
+                    // rather than take the min, set bit 16 so the answer is 16 when all matched
+                    // the two results x1,y1 are both < 16 bits, 
+                    // answer from _mm_tzcnt_32 would be 32 if all matched (as it takes u32 rather than u16)
+                    _mm_tzcnt_32( (x1 | y1 | 0x1_0000 ) as u32) as usize
 
                     // Shown to be slower then the AND trick is a "min" as it doesn't need a compare
                     //std::cmp::min(_mm_tzcnt_32( (x1 | y1 ) as u32), 16) as usize
@@ -363,34 +950,36 @@ impl <R: Read> JsonParser<R> {
             }
             
             let end_pos = self.buf_pos + num_digits;
-            // commented out to get "perf" to measure parse-time only
-            // unsafe {
-            //     self.string_buff.as_mut_vec().extend_from_slice( &self.buffer[self.buf_pos..end_pos]);
-            // }
-            
             self.buf_pos = end_pos;
+            total += num_digits;
             // if we got to the end of the buffer, reload and start again
-            // TODO: check boundary here?
-            if self.buf_pos < self.buffer.len() {
-                return Ok(true);
+            if self.buf_pos < self.buf().len() {
+                return Ok(total);
             } else {
                 self.replace_buffer()?;
+                // a long run of digits is kept in `string_buff`, so don't let it grow too far
+                if self.token_len(self.buf_pos) > self.config.max_number_len {
+                    return Err(self.token_too_long(Limit::NumberLength, self.config.max_number_len));
+                }
             }
         }
 
-        Ok(false)
+        // digits ran up to the end of the input
+        Ok(total)
 
    }
     
     /// Called only from match number, returns true if any digits matched
     /// // we'll try inlining, because only used in number check
+    /// Kept to compare against the SIMD version
+    #[allow(dead_code)]
     #[inline]
     fn match_digits(&mut self) -> ParseResult<bool> {
         
-        while !self.buffer.is_empty() {
+        while !self.buf().is_empty() {
 
-            while self.buf_pos < self.buffer.len() - 3 {
-                let s = &self.buffer[self.buf_pos..self.buf_pos+4];
+            while self.buf_pos < self.buf().len() - 3 {
+                let s = &self.buf()[self.buf_pos..self.buf_pos+4];
                 if !is_digit(s[0]) { return Ok(true)}
                 if !is_digit(s[1]) { self.buf_pos += 1; return Ok(true);}
                 if !is_digit(s[2]) { self.buf_pos += 2; return Ok(true);}
@@ -400,7 +989,7 @@ impl <R: Read> JsonParser<R> {
             }
 
             // and the remainder of buffer
-            let end_pos = self.buf_pos + self.buffer[self.buf_pos..self.buffer.len()]
+            let end_pos = self.buf_pos + self.buf()[self.buf_pos..self.buf().len()]
                 .iter()
                 //.take_while( |n| **n >= b'0' && **n <= b'9')
                 .take_while( |n| is_digit(**n))
@@ -408,7 +997,7 @@ impl <R: Read> JsonParser<R> {
 
 
             // let mut n = 0usize;
-            // for c in &self.buffer[self.buf_pos..self.buf_cap] {
+            // for c in &self.buf()[self.buf_pos..self.buf_cap] {
             //     if *c <  b'0' || *c > b'9'{
             //     // if !is_digit(*c) {
             //         break;
@@ -418,13 +1007,13 @@ impl <R: Read> JsonParser<R> {
             // let end_pos = self.buf_pos + n;
 
             // unsafe {
-            //     self.string_buff.as_mut_vec().extend_from_slice( &self.buffer[self.buf_pos..end_pos]);
+            //     self.string_buff.as_mut_vec().extend_from_slice( &self.buf()[self.buf_pos..end_pos]);
             // }
 
             self.buf_pos = end_pos;
                 
             // TODO: check boundary here?
-            if self.buf_pos < self.buffer.len() {
+            if self.buf_pos < self.buf().len() {
                 return Ok(true);
             }
 
@@ -434,60 +1023,131 @@ impl <R: Read> JsonParser<R> {
     }
 
 
-    // TODO: .. "12323E+22" style numbers, positive and negative
-    // TODO: also check all number formats 
-    /// Matches a number in the JSON input
-    fn match_number(&mut self) -> ParseResult<JsonEvent2> {
+    /// Matches a number in the JSON input, following the RFC 8259 grammar
+    ///     [ minus ] int [ frac ] [ exp ]
+    /// where int is a zero or digits not starting with zero
+    fn match_number(&mut self) -> ParseResult<Token> {
         
         // prob not necessary - we scan number only if matches
         // self.skip_whitespace() ?;
 
+        self.start_token();
+
         // very first could be a minus!
-        //if self.buf_pos < self.buffer.len() && self.buffer[self.buf_pos] == '-' as u8 {
-        //self.string_buff.clear();
-        if self.consume_if( U8_MINUS )? {
-            //self.string_buff.push('-');
-        }
+        let mut int_end = self.consume_if( U8_MINUS )? as usize;
 
-        // if no numbers, its a cockup
-        if !self.match_digits_simd()? {
-            return Err(ParseErr::DidNotMatch);
+        // integer part, a single zero or digits not starting with zero
+        if self.consume_if( U8_0 )? {
+            if let Some(n) = self.peek()? {
+                if is_digit(n) {
+                    return Err(Self::invalid_number("leading zeros are not allowed"));
+                }
+            }
+            int_end += 1;
+        } else {
+            let n = self.match_digits_simd()?;
+            if n == 0 {
+                // if no numbers, its a cockup
+                return Err(Self::invalid_number("expected a digit"));
+            }
+            int_end += n;
         }
 
-        //self.peeked.take(); // hack again
+        // fraction
+        let mut frac_end = int_end;
         if self.consume_if( U8_PERIOD )? {
-            //self.string_buff.push('.');
+            let n = self.match_digits_simd()?;
+            if n == 0 {
+                return Err(Self::invalid_number("expected a digit after the decimal point"));
+            }
+            frac_end += n + 1;
+        }
 
-            if !self.match_digits_simd()? {
-                return Err(ParseErr::DidNotMatch);
+        // exponent
+        if self.consume_if( U8_EXP )? || self.consume_if( U8_EXP_UPPER )? {
+            if !self.consume_if( U8_PLUS )? {
+                self.consume_if( U8_MINUS )?;
+            }
+            if self.match_digits_simd()? == 0 {
+                return Err(Self::invalid_number("expected a digit in the exponent"));
             }
         }
 
-        Ok(JsonEvent2::Number(&self.string_buff))
+        if self.token_len(self.buf_pos) > self.config.max_number_len {
+            return Err(self.token_too_long(Limit::NumberLength, self.config.max_number_len));
+        }
+        self.end_token(self.buf_pos);
+        Ok(Token::Number(int_end, frac_end))
+    }
+
+    /// Error for a badly formed number
+    #[cold]
+    fn invalid_number(reason: &'static str) -> ParseErr {
+        ErrorKind::InvalidNumber(reason).into()
 
     }
 
 
-    /// Matches a quoted string
+    /// Matches a quoted string, the contents between the quotes are left as the token text.
+    /// The opening quote must be the next byte.  Most strings are plain ASCII with the
+    /// closing quote in the buffer, so those are matched here, and anything else is left
+    /// to `match_string_from`
+    #[inline]
     fn match_string(&mut self) -> ParseResult<()> {
+        let start = self.buf_pos + 1;
+        let mut pos = start;
+        if self.resume.is_none() {
+            let buf = self.buf();
+            while pos + 16 <= buf.len() {
+                let n = Self::count_text_simd(&buf[pos..pos + 16]);
+                pos += n;
+                if n == 16 {
+                    continue;
+                }
+                if buf[pos] == U8_QUOTE && pos - start <= self.config.max_string_len {
+                    self.tok_start = None;
+                    self.tok_spilled = false;
+                    self.tok_text = (start, pos);
+                    self.buf_pos = pos + 1;
+                    return Ok(());
+                }
+                break;
+            }
+        }
+        self.match_string_from(pos)
+    }
 
-        self.match_char( U8_QUOTE ) ?;
+    /// Matches the rest of a string, which has been checked up to `checked`
+    #[inline(never)]
+    fn match_string_from(&mut self, checked: usize) -> ParseResult<()> {
 
-        // TODO: try and get directly into our required byte slice
-        //let mut s = String::new();
-        // self.string_buff.clear();
+        let quote = self.buf_offset + self.buf_pos;
+        self.buf_pos += 1;
+        self.start_token();
 
         // put this locally to attempt to localise the var .. put in register?
-        let mut pos = self.buf_pos;
-        
+        let mut pos = checked;
+        if self.resume.is_some() {
+            // nothing has been checked yet
+            self.resume_string(quote);
+            pos = self.buf_pos;
+        }
+
         loop { // end of string 
             loop { // to refill buffer
                 // find the next non-text char .. 
 
-                // loop unrolling here
-                if pos + 7 < self.buffer.len() /*&& !self.buffer.is_empty() */{
+                if pos + 16 <= self.buf().len() {
+                    // 16 at a time while we can
+                    let n = Self::count_text_simd(&self.buf()[pos..pos+16]);
+                    pos += n;
+                    if n != 16 {
+                        break;
+                    }
+                } else if pos + 7 < self.buf().len() /*&& !self.buf().is_empty() */{
+                    // loop unrolling here
                     
-                    let slice = &self.buffer[pos..pos+8];
+                    let slice = &self.buf()[pos..pos+8];
 
                     let c0 = slice[0];
                     let c1 = slice[1];
@@ -536,7 +1196,7 @@ impl <R: Read> JsonParser<R> {
                 } else {
                     // we need to slow check
                     let mut found = false;
-                    pos += self.buffer[pos..self.buffer.len()].iter()
+                    pos += self.buf()[pos..self.buf().len()].iter()
                         .take_while( |x| {
                             // found = is_not_text(**x);
                             // !found
@@ -555,30 +1215,231 @@ impl <R: Read> JsonParser<R> {
                 }
 
                 // if we haven't broke out .. continue
+                self.mark_string(quote, pos);
                 self.buf_pos = pos;
                 self.ensure_buffer() ?;
+                if self.buf().is_empty() {
+                    // EOF inside the string
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
                 pos = self.buf_pos;
+                // a long string is kept in `string_buff`, so don't let it grow too far
+                if self.token_len(pos) > self.config.max_string_len {
+                    return Err(self.token_too_long(Limit::StringLength, self.config.max_string_len));
+                }
             }
 
             // Well always have a char here
 
             // now actuall check the last char
-            if self.buffer[pos] == b'\\' {
-                // escape - just ignore next char for now, need to re-loop
-                // to collect rest of the String
-                pos += 1;
-            } else if self.buffer[pos] == b'"' { 
+            let c = self.buf()[pos];
+            if c == U8_ESCAPE {
+                // escape - from here on the string is built up in `string_buff`,
+                // the token restarts after the escape sequence
+                self.mark_string(quote, pos);
+                self.spill_token(pos);
+                self.tok_start = None;
+                self.buf_pos = pos + 1;
+                self.match_escape() ?;
+                self.tok_start = Some(self.buf_pos);
+                pos = self.buf_pos;
+            } else if c == U8_QUOTE { 
                 // break out of outer loop after passed the quote
-                pos +=1;
                 break;
+            } else if c < 0x20 {
+                self.buf_pos = pos;
+                return Err(ErrorKind::ControlCharacter(c).into());
+            } else {
+                pos = self.match_utf8_run(quote, pos)?;
             }
 
         }
-        self.buf_pos = pos;
+        if self.token_len(pos) > self.config.max_string_len {
+            return Err(self.token_too_long(Limit::StringLength, self.config.max_string_len));
+        }
+        self.buf_pos = pos + 1;
+        // every byte between the quotes has been checked as we went, and escapes 
+        // are decoded to UTF-8, so no need to check again
+        self.end_token(pos);
+        Ok(())
+    }
+
+    /// Notes how far the string that started at `quote` has got, before anything that
+    /// could run out of input
+    #[inline]
+    fn mark_string(&mut self, quote: usize, pos: usize) {
+        self.string_mark = Some((quote, self.buf_offset + pos, self.token_len(pos)));
+    }
+
+    /// Carries on with the string that started at `quote`, if the input ran out in it
+    /// last time.  Its text so far is still in `string_buff`
+    #[cold]
+    fn resume_string(&mut self, quote: usize) {
+        if let Some(r) = self.resume.take() {
+            if r.quote == quote {
+                self.string_buff.truncate(r.len);
+                self.tok_spilled = true;
+                self.buf_pos = r.at - self.buf_offset;
+                self.tok_start = Some(self.buf_pos);
+            }
+        }
+    }
+
+    /// How many bytes of the 16 are plain text, 16 if all of them.  
+    #[inline]
+    fn count_text_simd(b: &[u8]) -> usize {
+        use core::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_movemask_epi8, _mm_cmplt_epi8, _mm_cmpeq_epi8, _mm_or_si128, _mm_set1_epi8 };
+
+        debug_assert!(b.len() >= 16);
+        unsafe {
+            let vec = _mm_loadu_si128(b.as_ptr() as *const __m128i);
+            // signed compare, so catches both control chars and anything >= 128
+            let x = _mm_cmplt_epi8(vec, _mm_set1_epi8(0x20));
+            let y = _mm_cmpeq_epi8(vec, _mm_set1_epi8(U8_QUOTE as i8));
+            let z = _mm_cmpeq_epi8(vec, _mm_set1_epi8(U8_ESCAPE as i8));
+            let m = _mm_movemask_epi8(_mm_or_si128(x, _mm_or_si128(y, z)));
+            // bit 16 set so the answer is 16 when all are text
+            (m as u32 | 0x1_0000).trailing_zeros() as usize
+        }
+    }
+
+    /// Checks a run of non-ASCII text starting at `pos` is UTF-8, returning where the run ends.
+    /// `quote` is where the string started
+    #[inline(never)]
+    fn match_utf8_run(&mut self, quote: usize, pos: usize) -> ParseResult<usize> {
+        // the run goes up to anything that ends the string, or needs escaping
+        let run = &self.buf()[pos..];
+        let end = run.iter()
+            .position(|c| *c == U8_QUOTE || *c == U8_ESCAPE || *c < 0x20)
+            .unwrap_or(run.len());
+
+        match std::str::from_utf8(&run[..end]) {
+            Ok(_) => Ok(pos + end),
+            Err(e) if e.error_len().is_none() && pos + end == self.buf().len() => {
+                // last char is cut off by the end of the buffer, check it a byte at a time
+                let at = pos + e.valid_up_to();
+                self.mark_string(quote, at);
+                self.buf_pos = at;
+                self.match_utf8_char()?;
+                Ok(self.buf_pos)
+            },
+            Err(e) => {
+                self.buf_pos = pos + e.valid_up_to();
+                Err(Self::invalid_utf8())
+            },
+        }
+    }
+
+    /// Checks a single UTF-8 char, which can straddle a re-fill of the buffer
+    fn match_utf8_char(&mut self) -> ParseResult<()> {
+        // the char may not be in the buffer by the time we know it's bad
+        let at = self.position();
+        let mut utf8 = [self.next_byte()?, 0, 0, 0];
+        let len = match utf8[0] {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        for b in utf8[1..len].iter_mut() {
+            *b = self.next_byte()?;
+            // anything but a continuation byte is bad straight away
+            if *b & 0xC0 != 0x80 {
+                self.unread();
+                return Err(Self::invalid_utf8().at(at));
+            }
+        }
+        match std::str::from_utf8(&utf8[..len]) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Self::invalid_utf8().at(at)),
+        }
+    }
+
+    #[cold]
+    fn invalid_utf8() -> ParseErr {
+        ErrorKind::InvalidUtf8.into()
+    }
+
+    /// Next byte of input, running out is an error
+    #[inline]
+    fn next_byte(&mut self) -> ParseResult<u8> {
+        self.ensure_buffer() ?;
+        if self.buf_pos < self.buf().len() {
+            self.buf_pos += 1;
+            Ok(self.buf()[self.buf_pos - 1])
+        } else {
+            Err(ErrorKind::UnexpectedEof.into())
+        }
+    }
+
+    /// Steps back over the byte just returned by `next_byte`, so errors point at it
+    #[inline]
+    fn unread(&mut self) {
+        self.buf_pos -= 1;
+    }
+
+    /// Four hex digits of a unicode escape
+    fn match_hex4(&mut self) -> ParseResult<u32> {
+        let mut n = 0u32;
+        for _i in 0..4 {
+            let digit = match self.next_byte()? {
+                b @ b'0'..=b'9' => b - b'0',
+                b @ b'a'..=b'f' => b - b'a' + 10,
+                b @ b'A'..=b'F' => b - b'A' + 10,
+                _ => {
+                    self.unread();
+                    return Err(ErrorKind::InvalidEscape.into());
+                },
+            };
+            n = (n << 4) | digit as u32;
+        }
+        Ok(n)
+    }
+
+    /// Decodes the escape sequence after a backslash into `string_buff`
+    #[inline(never)]
+    fn match_escape(&mut self) -> ParseResult<()> {
+        let b = match self.next_byte()? {
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'/' => b'/',
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'u' => {
+                let mut c = self.match_hex4()?;
+                if (0xDC00..=0xDFFF).contains(&c) {
+                    return Err(ErrorKind::UnpairedSurrogate.into());
+                }
+                if (0xD800..=0xDBFF).contains(&c) {
+                    // high surrogate, must have the low surrogate next
+                    if !self.consume_if(U8_ESCAPE)? || !self.consume_if(b'u')? {
+                        return Err(ErrorKind::UnpairedSurrogate.into());
+                    }
+                    let low = self.match_hex4()?;
+                    if !(0xDC00..=0xDFFF).contains(&low) {
+                        return Err(ErrorKind::UnpairedSurrogate.into());
+                    }
+                    c = 0x10000 + ((c - 0xD800) << 10) + (low - 0xDC00);
+                }
+                // surrogates have been ruled out, so this is always a char
+                let c = std::char::from_u32(c).unwrap();
+                let mut utf8 = [0u8; 4];
+                self.string_buff.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                return Ok(());
+            }
+            _ => {
+                self.unread();
+                return Err(ErrorKind::InvalidEscape.into());
+            },
+        };
+        self.string_buff.push(b);
         Ok(())
     }
 
-    pub fn match_keyword(&mut self, b: u8) -> ParseResult<JsonEvent2> {
+    pub fn match_keyword(&mut self, b: u8) -> ParseResult<JsonEvent2<'static>> {
 
         // we've already skipped white sapce
         // self.skip_whitespace() ?;
@@ -588,58 +1449,74 @@ impl <R: Read> JsonParser<R> {
         // .. and then this "slow" version here, so we can poss make use of 
         // . .e.g vectorizing or using out internal buffer more intelligently
 
-        if b == 't' as u8 { 
+        if b == b't' { 
             //true
-            byte_seq!(self, 't', 'r', 'u', 'e');
+            byte_seq!(self, "'true'", b't', b'r', b'u', b'e');
             return Ok(JsonEvent2::Boolean(true));
-        } else if b == 'f' as u8 { 
+        } else if b == b'f' { 
             //false
-            byte_seq!(self, 'f', 'a', 'l', 's', 'e');
+            byte_seq!(self, "'false'", b'f', b'a', b'l', b's', b'e');
             return Ok(JsonEvent2::Boolean(false));
-        } else if b == 'n' as u8 { 
+        } else if b == b'n' { 
             //null
-            byte_seq!(self, 'n', 'u', 'l', 'l');
+            byte_seq!(self, "'null'", b'n', b'u', b'l', b'l');
             return Ok(JsonEvent2::Null);
         } 
 
-        Err(ParseErr::DidNotMatch)
+        Err(self.unexpected("a value"))
     }
 
     //  #[inline] - did no make much difference
-    fn it_match_value(&mut self) -> ParseResult<JsonEvent2> {
-        self.skip_whitespace() ?;
+    /// Whitespace has been skipped before entry
+    fn it_match_value(&mut self) -> ParseResult<Token> {
+        self.mark_token();
         // Peek the char
         match self.peek()? {
-            Some( U8_QUOTE ) => { self.match_string()?; 
-                Ok(JsonEvent2::String(&self.string_buff)) }
-            Some( U8_START_ARR ) => {self.stack.push(JsonStackItem::Array(0)); 
-                self.buf_pos += 1; Ok(JsonEvent2::ArrayStart)}
-            Some( U8_START_OBJ ) => {self.stack.push(JsonStackItem::Object(0)); 
-                self.buf_pos += 1; Ok(JsonEvent2::ObjectStart)}
-            Some ( n ) if (n >= U8_0 && n <= U8_9) || n == U8_MINUS => 
+            Some( U8_QUOTE ) => {self.match_string()?; Ok(Token::String)},
+            Some( U8_START_ARR ) => {self.push(JsonStackItem::Array(0))?; 
+                self.buf_pos += 1; Ok(Token::ArrayStart)}
+            Some( U8_START_OBJ ) => {self.push(JsonStackItem::Object(0, NameAt::Buffer(0, 0)))?; 
+                self.buf_pos += 1; Ok(Token::ObjectStart)}
+            Some ( n ) if (U8_0..=U8_9).contains(&n) || n == U8_MINUS => 
                 self.match_number(),
-            Some( b ) => self.match_keyword( b ) ,
-            _ => Err(ParseErr::DidNotMatch),
+            Some( b ) => match self.match_keyword( b )? {
+                JsonEvent2::Boolean(v) => Ok(Token::Boolean(v)),
+                _ => Ok(Token::Null),
+            },
+            None => Err(ErrorKind::UnexpectedEof.into()),
         }        
     }
 
+    /// Goes into an array or object, if not too deep
+    #[inline]
+    fn push(&mut self, item: JsonStackItem) -> ParseResult<()> {
+        if self.stack.len() >= self.config.max_depth {
+            return Err(ErrorKind::DepthExceeded.into());
+        }
+        self.stack.push(item);
+        Ok(())
+    }
+
     /// Match an array
-    fn it_match_obj_array(&mut self, n: usize) -> ParseResult<JsonEvent2> {
+    fn it_match_obj_array(&mut self, n: usize) -> ParseResult<Token> {
         // whitespace skipped before entry
         // self.skip_whitespace()?;
 
         // we can take end of object immediatley
         if self.consume_if(b']')? {
             // pop from stack, ensure char is consumed
-            self.stack.pop();
-            return Ok(JsonEvent2::ArrayEnd);
+            self.pop();
+            return Ok(Token::ArrayEnd);
         }
 
         // if not object end, check if we need comma or not
         if n != 0 {
-            self.match_char(b',')?;
+            self.match_char(b',', "',' or ']'")?;
             self.skip_whitespace()?;
         }
+        if n >= self.config.max_array_elements {
+            return Err(Self::limit_exceeded(Limit::ArrayElements, self.config.max_array_elements));
+        }
         // TODO: increment the object member counter!!
         if let Some(JsonStackItem::Array(n)) = self.stack.last_mut() {
             *n += 1
@@ -650,36 +1527,149 @@ impl <R: Read> JsonParser<R> {
 
     }
 
-    fn it_match_obj_member(&mut self, n: usize) -> ParseResult<JsonEvent2> {
+    /// Comes out of an array or object, dropping the object's member name
+    #[inline]
+    fn pop(&mut self) {
+        if let Some(JsonStackItem::Object(_, NameAt::Names(start, _))) = self.stack.pop() {
+            self.names.truncate(start);
+        }
+    }
+
+    fn it_match_obj_member(&mut self, n: usize) -> ParseResult<Token> {
+        // name already handed out, so we need the separator and the value
+        if self.after_name {
+            self.after_name = false;
+            self.match_char(b':', "':'") ?;
+            self.skip_whitespace() ? ;
+            return self.it_match_value();
+        }
+
         // we can take end of object immediatley
         // self.skip_whitespace()?;
         if self.consume_if(b'}')? {
             // pop from stack, ensure char is consumed
-            self.stack.pop();
-            return Ok(JsonEvent2::ObjectEnd);
+            self.pop();
+            return Ok(Token::ObjectEnd);
         }
 
         // if not object end, check if we need comma or not
         if n != 0 {
-            self.match_char(b',')?;
+            self.match_char(b',', "',' or '}'")?;
             self.skip_whitespace()?;
+            self.mark_token();
+        }
+        if n >= self.config.max_object_members {
+            return Err(Self::limit_exceeded(Limit::ObjectMembers, self.config.max_object_members));
         }
         // TODO: increment the object member counter!!
-        if let Some(JsonStackItem::Object(n)) = self.stack.last_mut() {
+        if let Some(JsonStackItem::Object(n, _)) = self.stack.last_mut() {
             *n += 1
         }
 
-        // value name
-        self.match_string() ? ;  
-        self.skip_whitespace() ? ;
-        self.match_char(':' as u8) ?;
-        self.skip_whitespace() ? ;
-        self.it_match_value()
+        // member name, the value follows on the next call
+        if self.peek()? != Some(U8_QUOTE) {
+            return Err(self.unexpected(if n == 0 { "a property name or '}'" } else { "a property name" }));
+        }
+        self.after_name = true;
+        let quote = self.buf_pos;
+        self.match_string()?;
+
+        // kept for the path, from between the quotes unless it's in `string_buff`,
+        // which is used again by the next token so the name is copied
+        if let Some(JsonStackItem::Object(_, name)) = self.stack.last_mut() {
+            if let NameAt::Names(start, _) = *name {
+                self.names.truncate(start);
+            }
+            *name = match self.tok_spilled {
+                false => NameAt::Buffer(quote + 1, self.buf_pos - 1),
+                true => NameAt::Buffer(0, 0),
+            };
+        }
+        if self.tok_spilled {
+            self.keep_names();
+            if let Some(JsonStackItem::Object(_, NameAt::Names(start, end))) = self.stack.last_mut() {
+                self.names.truncate(*start);
+                self.names.push_str(unsafe { std::str::from_utf8_unchecked(&self.string_buff) });
+                *end = self.names.len();
+            }
+        }
+        Ok(Token::PropertyName)
 
     }
 
-    /// Iteratir style function, returns the next token in the parse 
-    pub fn next_token(&mut self) -> ParseResult<Option<JsonEvent2>> {
+    /// Copies the names still in the buffer to `names`, before the buffer moves on
+    #[cold]
+    fn keep_names(&mut self) {
+        let buf = self.read.bytes(&self.buffer);
+        for item in self.stack.iter_mut() {
+            if let JsonStackItem::Object(_, name) = item {
+                if let NameAt::Buffer(start, end) = *name {
+                    let from = self.names.len();
+                    self.names.push_str(unsafe { std::str::from_utf8_unchecked(&buf[start..end]) });
+                    *name = NameAt::Names(from, self.names.len());
+                }
+            }
+        }
+    }
+
+    /// Iteratir style function, returns the next token in the parse.  Errors
+    /// carry the position in the input where they happened
+    pub fn next_token(&mut self) -> ParseResult<Option<JsonEvent2<'_>>> {
+        match self.next_located() {
+            Ok(Some(t)) => Ok(Some(self.event(t))),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The next token, leaving its text in the parser, with errors located as for
+    /// `next_token`
+    #[inline]
+    pub(crate) fn next_located(&mut self) -> ParseResult<Option<Token>> {
+        match self.next_raw_token() {
+            Ok(t) => Ok(t),
+            Err(e) => Err(self.locate(e)),
+        }
+    }
+
+    /// Adds where the error happened, in the input and, unless it's at the top
+    /// level, in the document
+    #[cold]
+    pub(crate) fn locate(&self, e: ParseErr) -> ParseErr {
+        let e = e.at(self.position());
+        match self.stack.is_empty() {
+            true => e,
+            false => e.in_path(self.path_text()),
+        }
+    }
+
+    /// The current path written out as a JSONPath, such as `$.orders[3].sku`
+    fn path_text(&self) -> String {
+        let mut text = String::from("$");
+        for segment in self.current_path() {
+            text.push_str(&segment.to_string());
+        }
+        text
+    }
+
+    /// The event for a token, with any text
+    #[inline]
+    pub(crate) fn event(&self, t: Token) -> JsonEvent2<'_> {
+        match t {
+            Token::String => JsonEvent2::String(self.token_text()),
+            Token::PropertyName => JsonEvent2::PropertyName(self.token_text()),
+            Token::Number(int_end, frac_end) => 
+                JsonEvent2::Number(JsonNumber::from_parts(self.token_text(), int_end, frac_end)),
+            Token::Boolean(b) => JsonEvent2::Boolean(b),
+            Token::Null => JsonEvent2::Null,
+            Token::ObjectStart => JsonEvent2::ObjectStart,
+            Token::ObjectEnd => JsonEvent2::ObjectEnd,
+            Token::ArrayStart => JsonEvent2::ArrayStart,
+            Token::ArrayEnd => JsonEvent2::ArrayEnd,
+        }
+    }
+
+    fn next_raw_token(&mut self) -> ParseResult<Option<Token>> {
         // if stack is empty, any valie JSON Value item can be next
         // println!("Stack len = {:?}", self.stack);
 
@@ -688,22 +1678,236 @@ impl <R: Read> JsonParser<R> {
         self.skip_whitespace() ?;
 
         // bit hacky .. check for EOF
-        if self.buffer.is_empty() {
-            return match self.stack.is_empty() {
+        if self.buf().is_empty() {
+            // a single document needs its value, a stream can be empty
+            return match self.stack.is_empty() && (self.root_done || self.multiple_values) {
                 true => Ok(None),
-                false => Err(ParseErr::DidNotMatch),
+                false => Err(ErrorKind::UnexpectedEof.into()),
             }
         }
+        self.mark_token();
 
         //let _b = self.peek()?;
-        match self.stack.last_mut() {
-            Some(JsonStackItem::Object(n)) => {let copy = *n; Ok(Some(self.it_match_obj_member(copy)?)) },
-            Some(JsonStackItem::Array(n)) => {let copy = *n; Ok(Some(self.it_match_obj_array(copy)?)) },
-            None => Ok(Some(self.it_match_value()? )),
+        let t = match self.stack.last_mut() {
+            Some(JsonStackItem::Object(n, _)) => {let copy = *n; self.it_match_obj_member(copy)? },
+            Some(JsonStackItem::Array(n)) => {let copy = *n; self.it_match_obj_array(copy)? },
+            None if self.root_done && !self.multiple_values => return Err(ErrorKind::TrailingData.into()),
+            None => self.it_match_value()?,
+        };
+        if self.stack.is_empty() {
+            self.root_done = true;
         }
-
+        Ok(Some(t))
     } 
 
+    /// How many arrays and objects we are inside
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Path from the top of the document to the value of the last event, such as
+    /// `["orders", 3, "sku"]` for the `sku` member of the fourth order.  For a member's
+    /// name it's the path of the member's value, and for the end of an array or object
+    /// it's the path of the array or object
+    pub fn current_path(&self) -> Vec<PathSegment<'_>> {
+        let mut path = Vec::with_capacity(self.stack.len());
+        for item in self.stack.iter() {
+            match *item {
+                JsonStackItem::Array(n) if n > 0 => path.push(PathSegment::Index(n - 1)),
+                JsonStackItem::Object(n, name) if n > 0 => {
+                    let name = match name {
+                        NameAt::Buffer(start, end) => unsafe { std::str::from_utf8_unchecked(&self.buf()[start..end]) },
+                        NameAt::Names(start, end) => &self.names[start..end],
+                    };
+                    path.push(PathSegment::Name(name));
+                },
+                // nothing in it yet
+                _ => (),
+            }
+        }
+        path
+    }
+
+    /// Skips the next value without handing out its events.  That's the value of the member
+    /// whose name was just returned, the next member of an object (name and value), the next
+    /// element of an array or the top level value.  Arrays and objects are skipped as for
+    /// `skip_children`.  False if there is no value to skip as the array or object ended,
+    /// in which case its end is skipped too, or the input ended
+    pub fn skip_value(&mut self) -> ParseResult<bool> {
+        loop {
+            let t = match self.next_raw_token() {
+                Ok(t) => t,
+                Err(e) => return Err(self.locate(e)),
+            };
+            match t {
+                // the member's value is next
+                Some(Token::PropertyName) => continue,
+                Some(Token::ObjectStart) | Some(Token::ArrayStart) => {
+                    self.skip_children()?;
+                    return Ok(true);
+                },
+                Some(Token::ObjectEnd) | Some(Token::ArrayEnd) | None => return Ok(false),
+                Some(_) => return Ok(true),
+            }
+        }
+    }
+
+    /// Skips the rest of the array or object we are in, including its end, so the next
+    /// event is whatever follows it.  Straight after `ObjectStart` or `ArrayStart` that's the
+    /// whole of it.  Much quicker than taking the events, as only brackets and strings are
+    /// looked at, so what's skipped isn't checked for errors beyond finding the end.
+    /// Does nothing at the top level
+    pub fn skip_children(&mut self) -> ParseResult<()> {
+        if self.stack.is_empty() {
+            return Ok(());
+        }
+        if let Err(e) = self.skip_nested() {
+            return Err(self.locate(e));
+        }
+        self.pop();
+        self.after_name = false;
+        if self.stack.is_empty() {
+            self.root_done = true;
+        }
+        Ok(())
+    }
+
+    /// Moves on past the end of the array or object we are in
+    fn skip_nested(&mut self) -> ParseResult<()> {
+        let mut nesting = 1usize;
+        let mut in_string = false;
+        loop {
+            let flags = if in_string { FLAG_IN_STRING } else { FLAG_STRUCT };
+            let pos = Self::find_flagged(self.buf(), self.buf_pos, flags);
+            if pos == self.buf().len() {
+                self.buf_pos = pos;
+                self.replace_buffer()?;
+                if self.buf().is_empty() {
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+                continue;
+            }
+
+            self.buf_pos = pos + 1;
+            match self.buf()[pos] {
+                U8_QUOTE => in_string = !in_string,
+                // only found in strings, the escaped byte could be a quote
+                U8_ESCAPE => {
+                    if self.next_byte()? == b'\n' {
+                        self.new_line(self.buf_pos - 1);
+                    }
+                },
+                b'\n' => self.new_line(pos),
+                U8_START_ARR | U8_START_OBJ => nesting += 1,
+                _ => {
+                    nesting -= 1;
+                    if nesting == 0 {
+                        return Ok(());
+                    }
+                },
+            }
+        }
+    }
+
+    /// Where the first byte from `pos` with any of the flags is, or the end of the buffer
+    #[inline]
+    fn find_flagged(b: &[u8], mut pos: usize, flags: u8) -> usize {
+        let flagged = |c: u8| CHAR_FLAGS[c as usize] & flags != 0;
+        // loop unrolling, as for strings
+        while pos + 8 <= b.len() {
+            let slice = &b[pos..pos + 8];
+            if flagged(slice[0]) {
+                return pos;
+            }
+            if flagged(slice[1]) {
+                return pos + 1;
+            }
+            if flagged(slice[2]) {
+                return pos + 2;
+            }
+            if flagged(slice[3]) {
+                return pos + 3;
+            }
+            if flagged(slice[4]) {
+                return pos + 4;
+            }
+            if flagged(slice[5]) {
+                return pos + 5;
+            }
+            if flagged(slice[6]) {
+                return pos + 6;
+            }
+            if flagged(slice[7]) {
+                return pos + 7;
+            }
+            pos += 8;
+        }
+        pos + b[pos..].iter().take_while(|c| !flagged(**c)).count()
+    }
+
+    /// True if the array or object we are in ends next, rather than having another
+    /// element or member
+    pub(crate) fn at_container_end(&mut self) -> ParseResult<bool> {
+        let end = self.skip_whitespace()
+            .and_then(|_| self.peek());
+        match end {
+            Ok(b) => Ok(b == Some(b']') || b == Some(b'}')),
+            Err(e) => Err(self.locate(e)),
+        }
+    }
+
+    /// Skips whitespace, including blank lines, true if that is the end of the input
+    pub(crate) fn at_end(&mut self) -> ParseResult<bool> {
+        loop {
+            self.skip_whitespace()?;
+            if !self.consume_if(b'\n')? {
+                return Ok(self.buf().is_empty());
+            }
+            self.new_line(self.buf_pos - 1);
+        }
+    }
+
+    /// Checks there is only whitespace left on the line, and moves on to the next line
+    pub(crate) fn end_line(&mut self) -> ParseResult<()> {
+        loop {
+            match self.peek()? {
+                Some(b' ') | Some(b'\t') | Some(b'\r') => self.buf_pos += 1,
+                Some(b'\n') => {
+                    self.new_line(self.buf_pos);
+                    self.buf_pos += 1;
+                    return Ok(());
+                },
+                None => return Ok(()),
+                Some(_) => return Err(ParseErr::from(ErrorKind::TrailingData).at(self.position())),
+            }
+        }
+    }
+
+    /// Forgets any value in progress and skips to the start of the next line, so
+    /// parsing can carry on after an error.  With `line_delimited`, an error never
+    /// gets past the newline that ends its line, so this is the line after it
+    pub(crate) fn skip_line(&mut self) -> ParseResult<()> {
+        self.stack.clear();
+        self.names.clear();
+        self.after_name = false;
+        self.tok_start = None;
+        loop {
+            self.ensure_buffer()?;
+            if self.buf().is_empty() {
+                return Ok(());
+            }
+            match self.buf()[self.buf_pos..].iter().position(|b| *b == b'\n') {
+                Some(n) => {
+                    self.buf_pos += n;
+                    self.new_line(self.buf_pos);
+                    self.buf_pos += 1;
+                    return Ok(());
+                },
+                None => self.buf_pos = self.buf().len(),
+            }
+        }
+    }
+
     /// read all bytes!
     pub fn count_all_bytes(&mut self) -> ParseResult<usize>{
         let mut result = 0usize;
@@ -711,13 +1915,13 @@ impl <R: Read> JsonParser<R> {
 
         let mut s = String::with_capacity(50);
 
-        while !self.buffer.is_empty() {
+        while !self.buf().is_empty() {
 
-            for c in &self.buffer[self.buf_pos..self.buffer.len()] {
+            for c in &self.buf()[self.buf_pos..self.buf().len()] {
                 if *c >= b'0' && *c <= b'9' {
                     result +=1;
                     s.push(*c as char);
-                } else if s.len() >0 {
+                } else if !s.is_empty() {
                     s.clear();
                 }
             }
@@ -733,6 +1937,129 @@ impl <R: Read> JsonParser<R> {
 }
 
 
+/// What a parser that is fed its input found next
+pub(crate) enum Fed {
+    Token(Token),
+    /// The end of the input, after a complete document
+    End,
+    /// The input ran out part way through a token
+    NeedInput,
+}
+
+
+/// A string the input ran out in, and where it got to
+struct StringResume {
+    /// Offset of the opening quote
+    quote: usize,
+    /// Offset to carry on checking it from
+    at: usize,
+    /// Length of its text in `string_buff`
+    len: usize,
+}
+
+
+/// Where a fed parser was before a token, so it can go back if the token isn't all there.
+/// The stack and `names` aren't kept, as a token only changes them once all of it has
+/// been scanned, apart from counting the element or member it starts, and copying names
+/// out of the buffer doesn't change the path
+struct Checkpoint {
+    buf_pos: usize,
+    buf_offset: usize,
+    line: usize,
+    line_start: usize,
+    tok_offset: usize,
+    /// Elements or members of the innermost array or object
+    count: usize,
+    after_name: bool,
+}
+
+
+impl JsonParser<FeedInput> {
+
+    /// Adds to the input.  Bytes already parsed are dropped first, so the
+    /// input only grows by what is still to be parsed
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> ParseResult<()> {
+        if self.read.ended {
+            return Err(ParseErr::BadData(String::from("Input given after its end")));
+        }
+        self.keep_names();
+        self.read.data.drain(..self.buf_pos);
+        self.buf_offset += self.buf_pos;
+        self.buf_pos = 0;
+
+        let max = self.config.max_document_size;
+        if self.too_long || self.buf_offset + self.read.data.len() + bytes.len() > max {
+            self.too_long = true;
+            return Err(Self::limit_exceeded(Limit::DocumentSize, max).at(self.position()));
+        }
+        self.read.data.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    /// There is no more input to come
+    pub(crate) fn end_input(&mut self) {
+        self.read.ended = true;
+    }
+
+    /// Size to read the input in
+    #[cfg(feature = "async")]
+    pub(crate) fn buf_size(&self) -> usize {
+        self.buf_size
+    }
+
+    /// Next token, if all of it has been fed.  If not, the parser is back where it
+    /// was, to try again once there is more.  A string carries on from where it got to,
+    /// so a long one fed a piece at a time is only checked once.  Numbers and keywords
+    /// are short, so they are scanned again from their start, as is any whitespace
+    /// and punctuation before the token.  Errors carry their position, as for `next_token`
+    pub(crate) fn next_fed(&mut self) -> ParseResult<Fed> {
+        let checkpoint = Checkpoint {
+            buf_pos: self.buf_pos,
+            buf_offset: self.buf_offset,
+            line: self.line,
+            line_start: self.line_start,
+            tok_offset: self.tok_offset,
+            count: match self.stack.last() {
+                Some(JsonStackItem::Array(n)) | Some(JsonStackItem::Object(n, _)) => *n,
+                None => 0,
+            },
+            after_name: self.after_name,
+        };
+        self.string_mark = None;
+        match self.next_raw_token() {
+            Ok(Some(t)) => Ok(Fed::Token(t)),
+            Ok(None) => Ok(Fed::End),
+            Err(ParseErr::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.keep_string();
+                self.rewind(checkpoint);
+                Ok(Fed::NeedInput)
+            },
+            Err(e) => Err(self.locate(e)),
+        }
+    }
+
+    /// Keeps where the string the input ran out in got to, if there is one
+    fn keep_string(&mut self) {
+        if let Some((quote, at, len)) = self.string_mark {
+            self.resume = Some(StringResume { quote, at, len });
+        }
+    }
+
+    fn rewind(&mut self, c: Checkpoint) {
+        self.buf_pos = c.buf_pos;
+        self.buf_offset = c.buf_offset;
+        self.line = c.line;
+        self.line_start = c.line_start;
+        self.tok_offset = c.tok_offset;
+        if let Some(JsonStackItem::Array(n)) | Some(JsonStackItem::Object(n, _)) = self.stack.last_mut() {
+            *n = c.count;
+        }
+        self.after_name = c.after_name;
+        self.tok_start = None;
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
 
@@ -740,15 +2067,20 @@ mod tests {
     use std::io::Cursor;
     
     /// Create parser used during tests
+    /// Small, so tests can easily have tokens that straddle a re-fill of the buffer
+    const TEST_BUFFER_SIZE: usize = 1000;
+
     fn test_parser(s: &str) -> JsonParser<Cursor<&str>> {
-        JsonParser::new(
-            Cursor::new(s)
-        )
+        test_builder().build(Cursor::new(s))
+    }
+
+    fn test_builder() -> JsonParserBuilder {
+        JsonParserBuilder::new().buffer_size(TEST_BUFFER_SIZE)
     }
 
 
     /// Little test helper
-    fn token<R: Read>(p: &mut JsonParser<R> ) -> JsonEvent2 {
+    fn token<R: JsonInput>(p: &mut JsonParser<R> ) -> JsonEvent2<'_> {
         p.next_token().unwrap().unwrap()
     }
 
@@ -770,20 +2102,33 @@ mod tests {
         let p = &mut p;
 
         assert!( token(p).is_obj_start() );
+            assert!( token(p).is_property_name() );
             assert!( token(p).is_string() );
+            assert!( token(p).is_property_name() );
             assert!( token(p).is_string() );
+            assert!( token(p).is_property_name() );
             assert!( token(p).is_string() );
+            assert!( token(p).is_property_name() );
             assert!( token(p).is_string() );
+            assert!( token(p).is_property_name() );
             assert!( token(p).is_string() );
+            assert!( token(p).is_property_name() ); // second
             assert!( token(p).is_obj_start() );
-                assert!( token(p).is_string() ); // Aardvark
+                assert!( token(p).is_property_name() ); // Aardvark
+                assert!( token(p).is_string() );
             assert!( token(p).is_obj_end() );
-            assert!( token(p).is_obj_start() ); // third
+            assert!( token(p).is_property_name() ); // third
+            assert!( token(p).is_obj_start() );
+                assert!( token(p).is_property_name() );
                 assert!( token(p).is_string() );
-                assert!( token(p).is_obj_start() ); // fourth
+                assert!( token(p).is_property_name() ); // fourth
+                assert!( token(p).is_obj_start() );
+                    assert!( token(p).is_property_name() );
                     assert!( token(p).is_string() ); 
                 assert!( token(p).is_obj_end() );
+                assert!( token(p).is_property_name() );
                 assert!( token(p).is_number() ); 
+                assert!( token(p).is_property_name() );
                 assert!( token(p).is_bool() ); 
             assert!( token(p).is_obj_end() );
         assert!( token(p).is_obj_end() );
@@ -817,6 +2162,7 @@ mod tests {
         assert!( token(p).is_arr_end() );
         assert!( token(p).is_number() );
         assert!( token(p).is_obj_start() );
+        assert!( token(p).is_property_name() );
         assert!( token(p).is_string() );
         assert!( token(p).is_obj_end() );
         assert!( token(p).is_bool() );
@@ -828,6 +2174,723 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_it_parse_property_names() -> ParseResult<()> {
+        let mut p = test_parser(r##"{"a": 1, "b" : {"c":[true]}, "d":{}}"##);
+        let p = &mut p;
+        assert!( token(p).is_obj_start() );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("a")) );
+        assert!( token(p).is_number() );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("b")) );
+        assert!( token(p).is_obj_start() );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("c")) );
+        assert!( token(p).is_arr_start() );
+        assert!( token(p).is_bool() );
+        assert!( token(p).is_arr_end() );
+        assert!( token(p).is_obj_end() );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("d")) );
+        assert!( token(p).is_obj_start() );
+        assert!( token(p).is_obj_end() );
+        assert!( token(p).is_obj_end() );
+        assert!( (p.next_token()?).is_none() );
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_values() -> ParseResult<()> {
+        let mut p = test_parser(r##"{"s": "hello world", "n": -12.5, "a": ["", "x", 7, 2.2142852745515373]}"##);
+        let p = &mut p;
+        assert!( token(p).is_obj_start() );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("s")) );
+        assert!( matches!(token(p), JsonEvent2::String("hello world")) );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("n")) );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "-12.5") );
+        assert!( matches!(token(p), JsonEvent2::PropertyName("a")) );
+        assert!( token(p).is_arr_start() );
+        assert!( matches!(token(p), JsonEvent2::String("")) );
+        assert!( matches!(token(p), JsonEvent2::String("x")) );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "7") );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "2.2142852745515373") );
+        assert!( token(p).is_arr_end() );
+        assert!( token(p).is_obj_end() );
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_values_across_refill() -> ParseResult<()> {
+        // pad so each value in turn straddles the end of the first buffer
+        let long = "abcdefghij".repeat(250);
+        for pad in TEST_BUFFER_SIZE - 50..TEST_BUFFER_SIZE {
+            let x = format!(r##"{}["name with spaces", -1234567.0987654321, "{}", true]"##, " ".repeat(pad), long);
+            let mut p = test_parser(&x);
+            let p = &mut p;
+            assert!( token(p).is_arr_start() );
+            assert!( matches!(token(p), JsonEvent2::String("name with spaces")) );
+            assert!( matches!(token(p), JsonEvent2::Number(n) if n == "-1234567.0987654321") );
+            match token(p) {
+                JsonEvent2::String(s) => assert_eq!(long, s),
+                e => panic!("unexpected {:?}", e),
+            }
+            assert!( matches!(token(p), JsonEvent2::Boolean(true)) );
+            assert!( token(p).is_arr_end() );
+        }
+        Ok(())
+    }
+
+    /// Parses a single string value
+    fn string_value(x: &str) -> ParseResult<String> {
+        let mut p = test_parser(x);
+        match p.next_token()? {
+            Some(JsonEvent2::String(s)) => Ok(s.to_string()),
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_it_string_escapes() -> ParseResult<()> {
+        assert_eq!("a\"b\\c/d", string_value(r##""a\"b\\c\/d""##)?);
+        assert_eq!("\u{8}\u{c}\n\r\t", string_value(r##""\b\f\n\r\t""##)?);
+        assert_eq!("caf\u{e9} \u{20ac}", string_value(r##""caf\u00e9 \u20AC""##)?);
+        assert_eq!("\u{1F600}!", string_value(r##""\ud83d\ude00!""##)?);
+        assert_eq!("\u{0}", string_value(r##""\u0000""##)?);
+        assert_eq!("caf\u{e9}", string_value("\"caf\u{e9}\"")?);
+
+        // names are decoded too
+        let mut p = test_parser(r##"{"a\nb":1}"##);
+        p.next_token()?;
+        assert!( matches!(p.next_token()?, Some(JsonEvent2::PropertyName("a\nb"))) );
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_string_bad_escapes() {
+        let tests = [
+            (r##""\x""##, ErrorKind::InvalidEscape),
+            (r##""\u12""##, ErrorKind::InvalidEscape),
+            (r##""\u12g4""##, ErrorKind::InvalidEscape),
+            (r##""\ud83d""##, ErrorKind::UnpairedSurrogate),
+            (r##""\ud83dx""##, ErrorKind::UnpairedSurrogate),
+            (r##""\ud83d\u0041""##, ErrorKind::UnpairedSurrogate),
+            (r##""\ude00""##, ErrorKind::UnpairedSurrogate),
+            (r##""abc\"##, ErrorKind::UnexpectedEof),
+        ];
+        for (x, kind) in tests.iter() {
+            let mut p = test_parser(x);
+            let e = p.next_token().expect_err(x);
+            assert_eq!(Some(kind), e.kind(), "{}", x);
+        }
+    }
+
+    #[test]
+    fn test_it_string_escapes_across_refill() -> ParseResult<()> {
+        for pad in TEST_BUFFER_SIZE - 30..TEST_BUFFER_SIZE {
+            let x = format!(r##"{}["ab\ud83d\ude00cd\n", "xyz"]"##, " ".repeat(pad));
+            let mut p = test_parser(&x);
+            let p = &mut p;
+            assert!( token(p).is_arr_start() );
+            assert!( matches!(token(p), JsonEvent2::String("ab\u{1F600}cd\n")) );
+            assert!( matches!(token(p), JsonEvent2::String("xyz")) );
+            assert!( token(p).is_arr_end() );
+        }
+        Ok(())
+    }
+
+    /// Parses a document that should be a single number
+    fn number_value(x: &str) -> ParseResult<String> {
+        let mut p = test_parser(x);
+        let n = match p.next_token()? {
+            Some(JsonEvent2::Number(n)) => {
+                // the parts found while scanning agree with parsing the text
+                assert_eq!(JsonNumber::parse(n.as_str())?, n);
+                n.to_string()
+            },
+            e => panic!("unexpected {:?}", e),
+        };
+        assert!( p.next_token()?.is_none() );
+        Ok(n)
+    }
+
+    #[test]
+    fn test_it_numbers() {
+        // input, and whether it is valid
+        let table = [
+            ("0", true), ("-0", true), ("7", true), ("-7", true), ("1234567890", true),
+            ("0.5", true), ("-0.5", true), ("12.34", true), ("1.000000000000000000001", true),
+            ("1e10", true), ("1E10", true), ("2.5E-3", true), ("1E+2", true), ("-1e-2", true),
+            ("0e0", true), ("0.0e+00", true), ("123456789012345678901234567890", true),
+            ("  42  ", true),
+            ("-", false), ("+1", false), (".5", false), ("0123", false), ("-01", false),
+            ("00", false), ("1.", false), ("1.e5", false), ("1e", false), ("1e+", false),
+            ("1E-", false), ("-.5", false), ("--1", false), ("1.5.5", false), ("1e5e5", false),
+            ("0x10", false), ("1ee5", false), ("NaN", false), ("Infinity", false), ("-Infinity", false),
+        ];
+        for (x, valid) in table.iter() {
+            match number_value(x) {
+                Ok(n) => {
+                    assert!(valid, "{} should not parse", x);
+                    assert_eq!(x.trim(), n);
+                },
+                Err(_) => assert!(!valid, "{} should parse", x),
+            }
+        }
+    }
+
+    #[test]
+    fn test_it_numbers_in_containers() -> ParseResult<()> {
+        let mut p = test_parser(r##"[0,-1.5e3,{"a":2E+0}, 1e-7]"##);
+        let p = &mut p;
+        assert!( token(p).is_arr_start() );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "0") );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "-1.5e3") );
+        assert!( token(p).is_obj_start() );
+        assert!( token(p).is_property_name() );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "2E+0") );
+        assert!( token(p).is_obj_end() );
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "1e-7") );
+        assert!( token(p).is_arr_end() );
+
+        // each part of the number in turn straddles the end of the buffer
+        for pad in TEST_BUFFER_SIZE - 17..TEST_BUFFER_SIZE {
+            let x = format!(r##"{}[-12345.678e-9]"##, " ".repeat(pad));
+            let mut p = test_parser(&x);
+            let p = &mut p;
+            assert!( token(p).is_arr_start() );
+            assert!( matches!(token(p), JsonEvent2::Number(n) if n == "-12345.678e-9") );
+            assert!( token(p).is_arr_end() );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_string_utf8() -> ParseResult<()> {
+        assert_eq!("gr\u{fc}\u{df}e \u{65e5}\u{672c}\u{8a9e} \u{1F600}\u{7f}", string_value("\"gr\u{fc}\u{df}e \u{65e5}\u{672c}\u{8a9e} \u{1F600}\u{7f}\"")?);
+        let long = "\u{65e5}\u{672c}\u{8a9e}\u{1F600}abc".repeat(100);
+        assert_eq!(long, string_value(&format!("\"{}\"", long))?);
+
+        // each multi-byte char in turn straddles the end of the buffer
+        for pad in TEST_BUFFER_SIZE - 32..TEST_BUFFER_SIZE {
+            let x = " ".repeat(pad) + "[\"\u{e9}\u{20ac}\u{1F600}\u{e9}\u{20ac}\u{1F600}\", \"x\"]";
+            let mut p = test_parser(&x);
+            let p = &mut p;
+            assert!( token(p).is_arr_start() );
+            assert!( matches!(token(p), JsonEvent2::String("\u{e9}\u{20ac}\u{1F600}\u{e9}\u{20ac}\u{1F600}")) );
+            assert!( matches!(token(p), JsonEvent2::String("x")) );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_string_bad_utf8() {
+        let bad: [&[u8]; 8] = [
+            b"\"a\x80b\"",
+            b"\"a\xc3\"",
+            b"\"a\xc3(\"",
+            b"\"a\xc0\xaf\"",
+            b"\"a\xed\xa0\x80\"",
+            b"\"a\xf4\x90\x80\x80\"",
+            b"\"a\xff\"",
+            b"\"abcdefghijklmnopqrstuvwxyz\xe6\x97\xa5\xe6\x97\"",
+        ];
+        for b in bad.iter() {
+            let mut p = JsonParser::new(Cursor::new(b.to_vec()));
+            match p.next_token() {
+                Err(e) => assert_eq!(Some(&ErrorKind::InvalidUtf8), e.kind()),
+                r => panic!("{:?} should not parse, got {:?}", b, r),
+            }
+        }
+
+        // position points at the bad byte
+        let mut p = JsonParser::new(Cursor::new(b"[\"ok\", \"\xe6\x97\xa5\xff\"]".to_vec()));
+        p.next_token().unwrap();
+        p.next_token().unwrap();
+        let e = p.next_token().unwrap_err();
+        assert_eq!(Some(11), e.position().map(|p| p.offset));
+    }
+
+    #[test]
+    fn test_it_positions() -> ParseResult<()> {
+        let mut p = test_parser("{\n  \"a\": [1,\n    true],\n  \"b\": \"x\"\n}");
+        assert_eq!(Position::start(), p.position());
+
+        // line and column of each token
+        let expected = [(1, 1), (2, 3), (2, 8), (2, 9), (3, 5), (3, 9), (4, 3), (4, 8), (5, 1)];
+        for (line, column) in expected.iter() {
+            p.next_token()?.unwrap();
+            let pos = p.token_position();
+            assert_eq!((*line, *column), (pos.line, pos.column));
+        }
+        assert_eq!(Position { offset: 36, line: 5, column: 2 }, p.position());
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_error_positions() {
+        let mut p = test_parser("[1,\n 2,\n  tru]");
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(Position { offset: 13, line: 3, column: 6 }), e.position());
+
+        // lines are counted across re-fills of the buffer
+        let mut text = String::from("[\n");
+        for _i in 0..5000 {
+            text.push_str("  1,\n");
+        }
+        text.push_str("  x]");
+        let mut p = test_parser(&text);
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(Position { offset: text.len() - 2, line: 5002, column: 3 }), e.position());
+
+        // and in what's skipped
+        let mut p = test_parser("[[1,\n \"a\\\nb\",\n {}],\n x]");
+        p.next_token().unwrap();
+        p.next_token().unwrap();
+        p.skip_children().unwrap();
+        let e = p.next_token().unwrap_err();
+        assert_eq!(Some(Position { offset: 21, line: 5, column: 2 }), e.position());
+    }
+
+    #[test]
+    fn test_it_string_control_chars() {
+        for x in &["\"a\nb\"", "\"\t\"", "\"\u{0}\"", "\"abc\u{1f}\"", "{\"a\rb\":1}"] {
+            let mut p = test_parser(x);
+            let mut failed = false;
+            for _i in 0..3 {
+                if let Err(e) = p.next_token() {
+                    assert!(matches!(e.kind(), Some(ErrorKind::ControlCharacter(_))), "unexpected {:?}", e);
+                    failed = true;
+                    break;
+                }
+            }
+            assert!(failed, "{:?} should not parse", x);
+        }
+    }
+
+    #[test]
+    fn test_it_parse_bad_members() {
+        // missing colon, missing value, name not a string
+        for x in &[r##"{"a" 1}"##, r##"{"a":}"##, r##"{a:1}"##, r##"{"a":1,}"##] {
+            let mut p = test_parser(x);
+            let mut failed = false;
+            for _i in 0..10 {
+                match p.next_token() {
+                    Ok(Some(_)) => {},
+                    Ok(None) => break,
+                    Err(_) => { failed = true; break; },
+                }
+            }
+            assert!(failed, "{} should not parse", x);
+        }
+    }
+
+    /// The first error parsing the text
+    fn first_error(text: &str) -> ParseErr {
+        let mut p = test_parser(text);
+        loop {
+            match p.next_token() {
+                Ok(Some(_)) => {},
+                Ok(None) => panic!("{} should not parse", text),
+                Err(e) => return e,
+            }
+        }
+    }
+
+    #[test]
+    fn test_it_error_kinds() {
+        let unexpected = |found, expected| ErrorKind::UnexpectedByte { found, expected };
+        let tests = [
+            (r##"{"a" 1}"##, unexpected(b'1', "':'")),
+            (r##"{"a":}"##, unexpected(b'}', "a value")),
+            (r##"{a:1}"##, unexpected(b'a', "a property name or '}'")),
+            (r##"{"a":1,}"##, unexpected(b'}', "a property name")),
+            (r##"{"a":1 "b":2}"##, unexpected(b'"', "',' or '}'")),
+            (r##"[1 2]"##, unexpected(b'2', "',' or ']'")),
+            (r##"[tru]"##, unexpected(b']', "'true'")),
+            (r##"[1,"##, ErrorKind::UnexpectedEof),
+            (r##"{"a":"##, ErrorKind::UnexpectedEof),
+            (r##"["abc"##, ErrorKind::UnexpectedEof),
+            (r##"[01]"##, ErrorKind::InvalidNumber("leading zeros are not allowed")),
+        ];
+        for (x, kind) in tests.iter() {
+            let e = first_error(x);
+            assert_eq!(Some(kind), e.kind(), "{}", x);
+            assert!(e.position().is_some());
+        }
+    }
+
+    #[test]
+    fn test_it_trailing_data() -> ParseResult<()> {
+        for x in &[r##"{"a":1} garbage"##, "1 2 3", "[1]]", r##""a""b""##, "null,", "{}{}"] {
+            assert_eq!(Some(&ErrorKind::TrailingData), first_error(x).kind(), "{}", x);
+        }
+
+        // whitespace after is fine, nothing at all is not
+        let mut p = test_parser(" [1] \n\t ");
+        while p.next_token()?.is_some() {}
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), first_error("").kind());
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), first_error("  ").kind());
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_multiple_values() -> ParseResult<()> {
+        let mut p = test_builder().multiple_values(true).build(Cursor::new(r##"1 "two"[3]{"four":4}null"##));
+        let p = &mut p;
+        assert!( matches!(token(p), JsonEvent2::Number(n) if n == "1") );
+        assert!( matches!(token(p), JsonEvent2::String("two")) );
+        assert!( token(p).is_arr_start() );
+        assert!( token(p).is_number() );
+        assert!( token(p).is_arr_end() );
+        assert!( token(p).is_obj_start() );
+        assert!( token(p).is_property_name() );
+        assert!( token(p).is_number() );
+        assert!( token(p).is_obj_end() );
+        assert!( token(p).is_null() );
+        assert!( p.next_token()?.is_none() );
+
+        // can be empty, but each value must be complete
+        assert!( test_builder().multiple_values(true).build(Cursor::new(" ")).next_token()?.is_none() );
+        let mut p = test_builder().multiple_values(true).build(Cursor::new("[1] [2"));
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), e.kind());
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_max_depth() -> ParseResult<()> {
+        let text = r##"[{"a": [1]}, {"b": {}}, [[]]]"##;
+        let mut p = test_builder().max_depth(3).build(Cursor::new(text));
+        while p.next_token()?.is_some() {}
+
+        let mut p = test_builder().max_depth(2).build(Cursor::new(text));
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+        // points at the array that's too deep
+        assert_eq!(Some(7), e.position().map(|p| p.offset));
+
+        // a bomb fails early, without using memory for the rest
+        let bomb = "[".repeat(1_000_000);
+        let mut p = test_builder().max_depth(64).build(Cursor::new(&bomb));
+        let mut n = 0;
+        let e = loop {
+            match p.next_token() {
+                Ok(_) => n += 1,
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(64, n);
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+
+        // nothing to nest in a scalar
+        assert!(test_builder().max_depth(0).build(Cursor::new("1")).next_token().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_limits() {
+        let config = JsonParserConfig {
+            max_string_len: 5,
+            max_number_len: 3,
+            max_object_members: 2,
+            max_array_elements: 3,
+            max_document_size: 200,
+            ..JsonParserConfig::default()
+        };
+        let parse = |text: &str| -> ParseResult<()> {
+            let mut p = JsonParserBuilder::new().config(config.clone()).build(Cursor::new(text.as_bytes().to_vec()));
+            while p.next_token()?.is_some() {}
+            Ok(())
+        };
+        let limit = |text: &str| match parse(text).unwrap_err().kind() {
+            Some(ErrorKind::LimitExceeded { limit, .. }) => *limit,
+            k => panic!("{} gave {:?}", text, k),
+        };
+
+        assert!(parse(r##"{"abcde": ["12345", 123, -12], "b": [1e2]}"##).is_ok());
+        assert_eq!(Limit::StringLength, limit(r##"["123456"]"##));
+        assert_eq!(Limit::StringLength, limit(r##"{"abcdef": 1}"##));
+        // escapes count once decoded, this is 6 bytes of UTF-8
+        assert_eq!(Limit::StringLength, limit(r##"["\u00e9\u00e9\u00e9"]"##));
+        assert_eq!(Limit::NumberLength, limit("[1234]"));
+        assert_eq!(Limit::NumberLength, limit("[1.25]"));
+        assert_eq!(Limit::ObjectMembers, limit(r##"{"a": 1, "b": 2, "c": 3}"##));
+        assert_eq!(Limit::ArrayElements, limit("[1, 2, 3, 4]"));
+        assert_eq!(Limit::DocumentSize, limit(&format!("[{}]", "1, ".repeat(100))));
+        assert_eq!("Number longer than the limit of 3 chars at line 1, column 2 in $[0]", 
+            parse("[1234]").unwrap_err().to_string());
+
+        // stops part way through long tokens, rather than keeping them
+        let config = JsonParserConfig { max_string_len: 100, max_number_len: 100, ..JsonParserConfig::default() };
+        for text in &[format!("\"{}\"", "x".repeat(100_000)), "9".repeat(100_000)] {
+            let mut p = JsonParserBuilder::new()
+                .buffer_size(TEST_BUFFER_SIZE)
+                .config(config.clone())
+                .build(Cursor::new(text.as_bytes()));
+            let e = p.next_token().unwrap_err();
+            assert!(matches!(e.kind(), Some(ErrorKind::LimitExceeded { .. })));
+            assert_eq!(Some(0), e.position().map(|p| p.offset));
+            assert!(p.position().offset < 10_000);
+        }
+
+        // the document size error is at the first byte over
+        let config = JsonParserConfig { max_document_size: 10, ..JsonParserConfig::default() };
+        let mut p = JsonParserBuilder::new().config(config).build(Cursor::new(b"[1, 2, 3, 4]".to_vec()));
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(10), e.position().map(|p| p.offset));
+
+        // and stays an error, without parsing any further
+        for _i in 0..3 {
+            let e = p.next_token().unwrap_err();
+            assert_eq!(Some(&ErrorKind::LimitExceeded { limit: Limit::DocumentSize, max: 10 }), e.kind());
+            assert_eq!(Some(10), e.position().map(|p| p.offset));
+        }
+        let mut p = JsonParserBuilder::new().max_document_size(3).build(SliceInput::new(b"[1] [2]"));
+        assert!(p.next_token().is_err());
+        assert!(p.skip_value().is_err());
+        assert!(p.next_token().is_err());
+        assert_eq!(3, p.position().offset);
+    }
+
+    #[test]
+    fn test_it_builder() -> ParseResult<()> {
+        let text = r##"{"a": [1, 2, {"b": "a string long enough to cross a few re-fills"}]} [3]"##;
+        for size in &[1, 2, 7, 16, 17, 100] {
+            let mut p = JsonParserBuilder::new()
+                .buffer_size(*size)
+                .string_capacity(0)
+                .stack_capacity(0)
+                .multiple_values(true)
+                .max_depth(3)
+                .build(Cursor::new(text));
+            let mut n = 0;
+            while let Some(e) = p.next_token()? {
+                if let JsonEvent2::String(s) = e {
+                    assert_eq!("a string long enough to cross a few re-fills", s);
+                }
+                n += 1;
+            }
+            assert_eq!(14, n);
+        }
+
+        let p = JsonParserBuilder::new().max_string_len(1).max_array_elements(2).build(Cursor::new("[]"));
+        assert_eq!(1, p.config().max_string_len);
+        assert_eq!(2, p.config().max_array_elements);
+        Ok(())
+    }
+
+    /// All the events, written out so two parses can be compared
+    fn all_events<R: JsonInput>(p: &mut JsonParser<R>) -> ParseResult<Vec<String>> {
+        let mut events = Vec::new();
+        while let Some(e) = p.next_token()? {
+            events.push(format!("{:?}", e));
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn test_it_from_slice() -> ParseResult<()> {
+        let text = r#"{"a": [1, -2.5e3, true, null], "b\n": "x\u00e9y", "c": {"d": "plain"}} "#;
+        let from_reader = all_events(&mut test_parser(text))?;
+        assert_eq!(from_reader, all_events(&mut JsonParser::from_slice(text.as_bytes()))?);
+        assert_eq!(16, from_reader.len());
+
+        // nothing is copied unless it has escapes
+        let range = text.as_bytes().as_ptr_range();
+        let mut p = JsonParser::from_slice(text.as_bytes());
+        while let Some(e) = p.next_token()? {
+            match e {
+                JsonEvent2::String(s) if s == "x\u{e9}y" => assert!(!range.contains(&s.as_ptr())),
+                JsonEvent2::String(s) | JsonEvent2::PropertyName(s) if s != "b\n" =>
+                    assert!(range.contains(&s.as_ptr()), "{} was copied", s),
+                JsonEvent2::Number(n) => assert!(range.contains(&n.as_str().as_ptr())),
+                _ => {},
+            }
+        }
+
+        // a number right at the end of the input
+        let mut p = JsonParser::from_slice(b"-12.5");
+        assert_eq!("-12.5", p.next_token()?.unwrap().as_number().unwrap().as_str());
+        assert!(p.next_token()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_next_borrowed() -> ParseResult<()> {
+        let text = r#"{"a": [1, -2.5e3, true, null], "b\n": "x\u00e9y", "c": {"d": "plain"}} "#;
+        let mut p = JsonParser::from_slice(text.as_bytes());
+        let first = p.next_borrowed()?.unwrap();
+        let name = p.next_borrowed()?.unwrap();
+        let mut events = vec![first, name];
+        while let Some(e) = p.next_borrowed()? {
+            events.push(e);
+        }
+        // the events outlive the calls after them, and the parser
+        drop(p);
+        assert_eq!(16, events.len());
+        assert!(events[0].is_obj_start());
+        assert!(matches!(&events[1], JsonEvent2::PropertyName(Cow::Borrowed("a"))));
+        assert_eq!("-2.5e3", events[4].as_number().unwrap().as_str());
+        assert!(matches!(&events[8], JsonEvent2::PropertyName(Cow::Owned(s)) if s == "b\n"));
+        assert!(matches!(&events[9], JsonEvent2::String(Cow::Owned(s)) if s == "x\u{e9}y"));
+        assert!(matches!(&events[13], JsonEvent2::String(Cow::Borrowed("plain"))));
+
+        // a number right at the end of the input is still borrowed
+        let n = JsonParser::from_slice(b" -12.5").next_borrowed()?;
+        let n = n.as_ref().and_then(|e| e.as_number()).unwrap();
+        assert_eq!("-12.5", n.as_str());
+        assert!(b" -12.5".as_ptr_range().contains(&n.as_str().as_ptr()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_from_slice_errors() {
+        let mut p = JsonParser::from_slice(b"[1,\n \"ab");
+        let e = loop {
+            if let Err(e) = p.next_token() {
+                break e;
+            }
+        };
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), e.kind());
+        assert_eq!(Some(Position { offset: 8, line: 2, column: 5 }), e.position());
+
+        let mut p = JsonParser::from_slice(b"");
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), p.next_token().unwrap_err().kind());
+
+        // options from the builder
+        let mut p = JsonParserBuilder::new().max_document_size(4).build(SliceInput::new(b"[1, 2]"));
+        let e = p.next_token().unwrap_err();
+        assert_eq!(Some(&ErrorKind::LimitExceeded { limit: Limit::DocumentSize, max: 4 }), e.kind());
+        assert_eq!(Some(4), e.position().map(|p| p.offset));
+
+        let mut p = JsonParserBuilder::new().multiple_values(true).build(SliceInput::new(b"1 [2] 3"));
+        assert_eq!(5, all_events(&mut p).unwrap().len());
+    }
+
+    #[test]
+    fn test_it_skip() -> ParseResult<()> {
+        let text = r#"{"skip": {"a": [1, {"b": "]}\"["}], "c": "\\"}, "want": 1,
+            "arr": [[1, 2], "x", {"y": null}, 3], "more": [4, 5, 6], "last": true}"#;
+        // small buffers, so skipping crosses re-fills, even between an escape and what it escapes
+        for size in &[1, 2, 3, 7, 1000] {
+            let mut p = JsonParserBuilder::new().buffer_size(*size).build(Cursor::new(text));
+            assert!(token(&mut p).is_obj_start());
+            assert!(matches!(token(&mut p), JsonEvent2::PropertyName("skip")));
+            assert!(p.skip_value()?);
+            assert!(matches!(token(&mut p), JsonEvent2::PropertyName("want")));
+            assert!(p.skip_value()?);
+
+            // a whole element at a time
+            assert!(matches!(token(&mut p), JsonEvent2::PropertyName("arr")));
+            assert!(token(&mut p).is_arr_start());
+            assert!(p.skip_value()?);
+            assert!(p.skip_value()?);
+            assert!(p.skip_value()?);
+            assert_eq!("3", token(&mut p).as_number().unwrap().as_str());
+            assert!(!p.skip_value()?);
+            assert_eq!(1, p.depth());
+
+            // the rest of an array part way through
+            assert!(matches!(token(&mut p), JsonEvent2::PropertyName("more")));
+            assert!(token(&mut p).is_arr_start());
+            assert!(token(&mut p).is_number());
+            p.skip_children()?;
+            assert_eq!(1, p.depth());
+
+            // a whole member, then the rest of the document
+            assert!(p.skip_value()?);
+            assert!(!p.skip_value()?);
+            assert_eq!(0, p.depth());
+            assert!(p.next_token()?.is_none());
+        }
+
+        // the rest of the top level object, from straight after its start
+        let mut p = test_parser(r#"{"a": [{}]} "#);
+        assert!(token(&mut p).is_obj_start());
+        p.skip_children()?;
+        assert!(p.next_token()?.is_none());
+
+        let mut p = test_parser("[1, [2, 3]");
+        assert!(p.skip_value().is_err());
+        let mut p = test_parser("[1, \"]");
+        assert!(token(&mut p).is_arr_start());
+        let e = p.skip_children().unwrap_err();
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), e.kind());
+        assert_eq!(Some(6), e.position().map(|p| p.offset));
+        Ok(())
+    }
+
+    #[test]
+    fn test_current_path() -> ParseResult<()> {
+        let mut p = JsonParserBuilder::new().buffer_size(5)
+            .build(Cursor::new(r#"{"orders": [{"id": "a"}, {"id": "b", "lines": [{"sku": "x"}, [], {"odd 'name'": true}]}], "n": null}"#));
+        let mut paths = Vec::new();
+        while let Some(e) = p.next_token()? {
+            let e = format!("{:?}", e);
+            let path: String = p.current_path().iter().map(|s| s.to_string()).collect();
+            paths.push(format!("{} ${}", e, path));
+        }
+        assert_eq!(vec![
+            "ObjectStart $",
+            "PropertyName(\"orders\") $.orders", "ArrayStart $.orders",
+            "ObjectStart $.orders[0]", "PropertyName(\"id\") $.orders[0].id", "String(\"a\") $.orders[0].id", "ObjectEnd $.orders[0]",
+            "ObjectStart $.orders[1]", "PropertyName(\"id\") $.orders[1].id", "String(\"b\") $.orders[1].id",
+            "PropertyName(\"lines\") $.orders[1].lines", "ArrayStart $.orders[1].lines",
+            "ObjectStart $.orders[1].lines[0]", "PropertyName(\"sku\") $.orders[1].lines[0].sku",
+            "String(\"x\") $.orders[1].lines[0].sku", "ObjectEnd $.orders[1].lines[0]",
+            "ArrayStart $.orders[1].lines[1]", "ArrayEnd $.orders[1].lines[1]",
+            "ObjectStart $.orders[1].lines[2]", "PropertyName(\"odd 'name'\") $.orders[1].lines[2]['odd \\'name\\'']",
+            "Boolean(true) $.orders[1].lines[2]['odd \\'name\\'']", "ObjectEnd $.orders[1].lines[2]",
+            "ArrayEnd $.orders[1].lines", "ObjectEnd $.orders[1]", "ArrayEnd $.orders",
+            "PropertyName(\"n\") $.n", "Null $.n", "ObjectEnd $",
+        ], paths);
+
+        let mut p = test_parser(r#"{"orders": [{"sku": "a"}, {"sku": "b"}, {"sku": "c"}, {"sku": "d"}]}"#);
+        while !matches!(p.next_token()?, Some(JsonEvent2::String("d"))) {}
+        assert_eq!(vec![PathSegment::Name("orders"), PathSegment::Index(3), PathSegment::Name("sku")], p.current_path());
+        assert_eq!(3, p.depth());
+        // names are only copied when the buffer moves on
+        assert!(p.names.is_empty());
+
+        let e = first_error(r#"{"orders": [{"sku": "a"}, {"sku": "b", "qty": x}]}"#);
+        assert_eq!(Some("$.orders[1].qty"), e.path());
+        assert!(e.to_string().ends_with(" in $.orders[1].qty"), "{}", e);
+        Ok(())
+    }
+
+    #[test]
+    fn test_it_error_display() {
+        let e = first_error("[1,\n 2 3]");
+        assert_eq!("Unexpected '3', expected ',' or ']' at line 2, column 4 in $[1]", e.to_string());
+        assert_eq!("Unexpected end of input at line 1, column 8 in $.a", first_error("{\"a\": [").to_string());
+
+        // can be used as a std error
+        fn parse_all(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+            let mut p = test_parser(text);
+            while p.next_token()?.is_some() {}
+            Ok(())
+        }
+        let e = parse_all("[\u{1}]").unwrap_err();
+        assert_eq!("Unexpected byte 0x01, expected a value at line 1, column 2 in $[0]", e.to_string());
+    }
+
 
     // /// Create a byemask from a char
     //  fn byte_mask_128(b: u8) -> u128 {
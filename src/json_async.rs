@@ -0,0 +1,198 @@
+//! Pull parser reading from a tokio `AsyncRead`, for when the input comes from a socket
+//! or anything else that shouldn't block a thread.  It runs the same parser as
+//! `JsonParser`, fed with the input as it is read.  As for `JsonPushParser`, a string
+//! split between reads carries on from where it got to, so a slow reader doesn't
+//! make a long string any slower to parse
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use crate::internals::{ParseResult, ParseErr, Position};
+use crate::json2::{JsonParser, JsonParserBuilder, JsonEvent2, FeedInput, Fed, PathSegment};
+
+
+/// Gives the events of the JSON read from `R`, each as soon as all of it has been read
+pub struct AsyncJsonParser<R: AsyncRead + Unpin> {
+    read: R,
+
+    parser: JsonParser<FeedInput>,
+
+    /// What is read at a time, before being handed to the parser
+    chunk: Vec<u8>,
+}
+
+
+impl <R: AsyncRead + Unpin> AsyncJsonParser<R> {
+
+    pub fn new(r: R) -> AsyncJsonParser<R> {
+        Self::with_builder(r, JsonParserBuilder::new())
+    }
+
+    /// Parser set up with the options and limits of the builder
+    pub fn with_builder(r: R, builder: JsonParserBuilder) -> AsyncJsonParser<R> {
+        let parser = builder.build(FeedInput::default());
+        AsyncJsonParser {
+            read: r,
+            chunk: vec![0; parser.buf_size()],
+            parser,
+        }
+    }
+
+    /// The next event, waiting for more input if we don't have all of it yet.
+    /// `None` at the end of the input, as for `JsonParser::next_token`
+    pub async fn next_token(&mut self) -> ParseResult<Option<JsonEvent2<'_>>> {
+        loop {
+            match self.parser.next_fed()? {
+                Fed::Token(t) => return Ok(Some(self.parser.event(t))),
+                Fed::End => return Ok(None),
+                Fed::NeedInput => self.read_more().await?,
+            }
+        }
+    }
+
+    async fn read_more(&mut self) -> ParseResult<()> {
+        match self.read.read(&mut self.chunk).await {
+            Ok(0) => self.parser.end_input(),
+            Ok(n) => self.parser.feed(&self.chunk[..n])?,
+            Err(e) => return Err(ParseErr::Io(e).at(self.parser.position())),
+        }
+        Ok(())
+    }
+
+    /// How many arrays and objects we are inside
+    pub fn depth(&self) -> usize {
+        self.parser.depth()
+    }
+
+    /// Path from the top of the document to the value of the last event, see
+    /// `JsonParser::current_path`
+    pub fn current_path(&self) -> Vec<PathSegment<'_>> {
+        self.parser.current_path()
+    }
+
+    /// Position in the input of the start of the last token returned
+    pub fn token_position(&self) -> Position {
+        self.parser.token_position()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::internals::ErrorKind;
+    use tokio::io::AsyncWriteExt;
+
+    /// All the events, written out so they can be compared with `JsonParser`'s
+    async fn all_events<R: AsyncRead + Unpin>(p: &mut AsyncJsonParser<R>) -> ParseResult<Vec<String>> {
+        let mut events = Vec::new();
+        while let Some(e) = p.next_token().await? {
+            events.push(format!("{:?}", e));
+        }
+        Ok(events)
+    }
+
+    fn sync_events(text: &str) -> ParseResult<Vec<String>> {
+        let mut p = JsonParser::from_slice(text.as_bytes());
+        let mut events = Vec::new();
+        while let Some(e) = p.next_token()? {
+            events.push(format!("{:?}", e));
+        }
+        Ok(events)
+    }
+
+    const TEXT: &str = r#"{"name": "async \"parsing\" é", "values": [1, -2.5e10, true, false, null],
+        "nested": {"empty": [], "obj": {}}, "big": 12345678901234567890}"#;
+
+    #[tokio::test]
+    async fn test_events_as_written() -> ParseResult<()> {
+        // the writer sends a few bytes at a time, so tokens are split between reads
+        let (mut client, server) = tokio::io::duplex(7);
+        let writer = async move {
+            for piece in TEXT.as_bytes().chunks(3) {
+                client.write_all(piece).await.unwrap();
+            }
+        };
+        let mut p = AsyncJsonParser::with_builder(server, JsonParserBuilder::new().buffer_size(5));
+        let (_, events) = tokio::join!(writer, all_events(&mut p));
+        assert_eq!(sync_events(TEXT)?, events?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_long_string_read_slowly() -> ParseResult<()> {
+        // thousands of reads end part way through the string, some in escapes and chars
+        let text: String = (0..100_000).map(|i| match i % 50 {
+            0 => "\\u00e9",
+            10 => "é",
+            20 => "\\ud83d\\ude00",
+            30 => "😀",
+            _ => "x",
+        }).collect();
+        let json = format!("{{\"long\":\n \"{}\", \"after\": 1}}", text);
+        let (mut client, server) = tokio::io::duplex(7);
+        let bytes = json.clone().into_bytes();
+        let writer = async move {
+            for piece in bytes.chunks(3) {
+                client.write_all(piece).await.unwrap();
+            }
+        };
+        let reader = async {
+            let mut p = AsyncJsonParser::new(server);
+            let mut events = Vec::new();
+            while let Some(e) = p.next_token().await? {
+                events.push(format!("{:?}", e));
+                if e.is_string() {
+                    let at = p.token_position();
+                    assert_eq!((2, 2), (at.line, at.column));
+                }
+            }
+            Ok::<_, ParseErr>(events)
+        };
+        let (_, events) = tokio::join!(writer, reader);
+        assert_eq!(sync_events(&json)?, events?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_whole_input() -> ParseResult<()> {
+        let mut p = AsyncJsonParser::new(TEXT.as_bytes());
+        assert_eq!(sync_events(TEXT)?, all_events(&mut p).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let mut p = AsyncJsonParser::new("[1,\n 2 3]".as_bytes());
+        let e = loop {
+            if let Err(e) = p.next_token().await {
+                break e;
+            }
+        };
+        assert_eq!("Unexpected '3', expected ',' or ']' at line 2, column 4 in $[1]", e.to_string());
+
+        // ending part way through
+        for text in &["", "[1, 2", "{\"a\": \"b", "12.", "tru"] {
+            let mut p = AsyncJsonParser::with_builder(text.as_bytes(), JsonParserBuilder::new().buffer_size(2));
+            let e = all_events(&mut p).await.unwrap_err();
+            assert!(matches!(e.kind(), Some(ErrorKind::UnexpectedEof) | Some(ErrorKind::InvalidNumber(_))), "{}: {}", text, e);
+        }
+
+        let builder = JsonParserBuilder::new().buffer_size(4).max_document_size(10).max_depth(2);
+        let e = all_events(&mut AsyncJsonParser::with_builder(TEXT.as_bytes(), builder.clone())).await.unwrap_err();
+        assert!(matches!(e.kind(), Some(ErrorKind::LimitExceeded { .. })));
+        let e = all_events(&mut AsyncJsonParser::with_builder("[[[]]]".as_bytes(), builder)).await.unwrap_err();
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+    }
+
+    #[tokio::test]
+    async fn test_positions() -> ParseResult<()> {
+        let mut p = AsyncJsonParser::with_builder("[\n  \"a\",\n  22]".as_bytes(), JsonParserBuilder::new().buffer_size(3));
+        let mut lines = Vec::new();
+        while p.next_token().await?.is_some() {
+            let at = p.token_position();
+            lines.push((at.line, at.column));
+        }
+        assert_eq!(vec![(1, 1), (2, 3), (3, 3), (3, 5)], lines);
+        Ok(())
+    }
+}
@@ -0,0 +1,610 @@
+//! serde `Deserializer` that fills values straight from the parser's events, without
+//! building a `JsonValue` first.  Strings are borrowed from the input when it is a slice
+//! and they have no escapes, enums are externally tagged (`"Unit"` or `{"Variant": ...}`)
+//! and anything not wanted, such as unknown fields, is skipped without being parsed
+
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, Visitor, Unexpected};
+use crate::internals::{ParseResult, ParseErr, ErrorKind};
+use crate::json2::{JsonParser, JsonInput, JsonEvent2, SliceInput, Token};
+use crate::json_number::JsonNumber;
+
+
+/// Value of the type from a document that is all in memory, borrowing strings from it
+/// where it can
+pub fn from_slice<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> ParseResult<T> {
+    let mut de = Deserializer::new(JsonParser::from_slice(bytes));
+    let value = de.parse()?;
+    de.end()?;
+    Ok(value)
+}
+
+/// Value of the type from a document that is all in memory, see `from_slice`
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> ParseResult<T> {
+    from_slice(s.as_bytes())
+}
+
+/// Value of the type from a document read from `r`.  Nothing can be borrowed, as
+/// the input doesn't last
+pub fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> ParseResult<T> {
+    let mut de = Deserializer::new(JsonParser::new(r));
+    let value = de.parse()?;
+    de.end()?;
+    Ok(value)
+}
+
+
+impl de::Error for ParseErr {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseErr::BadData(msg.to_string())
+    }
+}
+
+
+/// Input a `Deserializer` can read, which says whether the text of a token can be
+/// borrowed for as long as the input
+pub trait DeInput<'de>: JsonInput + Sized {
+    /// The text of the last token, if it's there as it is for all of `'de`
+    fn borrowed(p: &JsonParser<Self>) -> Option<&'de str>;
+}
+
+impl <'de, R: Read> DeInput<'de> for R {
+    #[inline]
+    fn borrowed(_p: &JsonParser<R>) -> Option<&'de str> {
+        None
+    }
+}
+
+impl <'de> DeInput<'de> for SliceInput<'de> {
+    #[inline]
+    fn borrowed(p: &JsonParser<SliceInput<'de>>) -> Option<&'de str> {
+        p.borrowed_text()
+    }
+}
+
+
+/// How deeply arrays and objects can nest before `Deserializer` gives up, as each
+/// level is a call deeper into the `Deserialize` impls
+const MAX_DEPTH: usize = 128;
+
+
+/// Deserializer over a `JsonParser`.  Each `parse` takes the next top level value, so
+/// with `multiple_values` a stream can be read one value at a time
+pub struct Deserializer<'de, R: DeInput<'de>> {
+    parser: JsonParser<R>,
+
+    /// A token looked at but not yet used, its text is still in the parser
+    peeked: Option<Token>,
+
+    /// How many more arrays and objects can be entered
+    depth_left: usize,
+
+    marker: PhantomData<&'de ()>,
+}
+
+
+impl <'de, R: DeInput<'de>> Deserializer<'de, R> {
+
+    /// Deserializer that allows arrays and objects to nest 128 deep, going deeper is
+    /// `ErrorKind::DepthExceeded`.  The parser's own `max_depth` applies as well
+    pub fn new(parser: JsonParser<R>) -> Deserializer<'de, R> {
+        Deserializer { parser, peeked: None, depth_left: MAX_DEPTH, marker: PhantomData }
+    }
+
+    /// Changes how deeply arrays and objects can nest.  Each level takes call stack,
+    /// so only raise this with the stack to spare
+    pub fn max_depth(mut self, depth: usize) -> Deserializer<'de, R> {
+        self.depth_left = depth;
+        self
+    }
+
+    /// The next value.  Errors carry where they happened, in the input and in the
+    /// document, as for `JsonParser::next_token`
+    pub fn parse<T: Deserialize<'de>>(&mut self) -> ParseResult<T> {
+        match T::deserialize(&mut *self) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(self.parser.locate(e)),
+        }
+    }
+
+    /// Checks there is nothing but whitespace after the values taken
+    pub fn end(&mut self) -> ParseResult<()> {
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(self.parser.locate(ErrorKind::TrailingData.into())),
+        }
+    }
+
+    /// The parser, say to carry on with events after a value
+    pub fn into_parser(self) -> JsonParser<R> {
+        self.parser
+    }
+
+    fn peek(&mut self) -> ParseResult<Option<Token>> {
+        if self.peeked.is_none() {
+            self.peeked = self.parser.next_located()?;
+        }
+        Ok(self.peeked)
+    }
+
+    fn next(&mut self) -> ParseResult<Token> {
+        if let Some(t) = self.peeked.take() {
+            return Ok(t);
+        }
+        match self.parser.next_located()? {
+            Some(t) => Ok(t),
+            None => Err(self.parser.locate(ErrorKind::UnexpectedEof.into())),
+        }
+    }
+
+    /// Gives the visitor the text of a string or member name, borrowed if it can be
+    fn visit_text<V: Visitor<'de>>(&self, visitor: V) -> ParseResult<V::Value> {
+        match R::borrowed(&self.parser) {
+            Some(s) => visitor.visit_borrowed_str(s),
+            None => visitor.visit_str(self.parser.token_text()),
+        }
+    }
+
+    /// Goes into an array or object, if that's not too deep
+    fn descend(&mut self) -> ParseResult<()> {
+        match self.depth_left.checked_sub(1) {
+            Some(left) => {
+                self.depth_left = left;
+                Ok(())
+            },
+            None => Err(ErrorKind::DepthExceeded.into()),
+        }
+    }
+
+    /// Takes the end of the array or object that a visitor has had all it wants of
+    fn end_container(&mut self, end: &str) -> ParseResult<()> {
+        self.depth_left += 1;
+        match self.next()? {
+            Token::ArrayEnd | Token::ObjectEnd => Ok(()),
+            _ => Err(ParseErr::BadData(format!("Expected the end of the {}", end))),
+        }
+    }
+}
+
+
+/// Gives the visitor a number as the first of u64, i64 or f64 that holds it
+fn visit_number<'de, V: Visitor<'de>>(n: &JsonNumber<'_>, visitor: V) -> ParseResult<V::Value> {
+    if n.is_integer() {
+        if !n.is_negative() {
+            if let Ok(u) = n.as_u64() {
+                return visitor.visit_u64(u);
+            }
+        } else if let Ok(i) = n.as_i64() {
+            return visitor.visit_i64(i);
+        }
+    }
+    visitor.visit_f64(n.as_f64()?)
+}
+
+
+impl <'de, R: DeInput<'de>> de::Deserializer<'de> for &mut Deserializer<'de, R> {
+    type Error = ParseErr;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.next()? {
+            Token::String | Token::PropertyName => self.visit_text(visitor),
+            t @ Token::Number(..) => match self.parser.event(t) {
+                JsonEvent2::Number(n) => visit_number(&n, visitor),
+                _ => unreachable!(),
+            },
+            Token::Boolean(b) => visitor.visit_bool(b),
+            Token::Null => visitor.visit_unit(),
+            Token::ArrayStart => {
+                self.descend()?;
+                let value = visitor.visit_seq(SeqAccess { de: self })?;
+                self.end_container("array")?;
+                Ok(value)
+            },
+            Token::ObjectStart => {
+                self.descend()?;
+                let value = visitor.visit_map(MapAccess { de: self })?;
+                self.end_container("object")?;
+                Ok(value)
+            },
+            Token::ArrayEnd | Token::ObjectEnd => Err(ParseErr::BadData(String::from("Expected a value"))),
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.peek()? {
+            Some(t @ Token::Number(..)) => {
+                self.peeked = None;
+                match self.parser.event(t) {
+                    JsonEvent2::Number(n) => visitor.visit_i128(n.as_i128()?),
+                    _ => unreachable!(),
+                }
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.peek()? {
+            Some(t @ Token::Number(..)) => {
+                self.peeked = None;
+                match self.parser.event(t) {
+                    JsonEvent2::Number(n) => visitor.visit_u128(n.as_u128()?),
+                    _ => unreachable!(),
+                }
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.peek()? {
+            Some(Token::Null) => {
+                self.peeked = None;
+                visitor.visit_none()
+            },
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> ParseResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Externally tagged, a string for a unit variant, otherwise an object with the
+    /// variant's name as its one member
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+            visitor: V) -> ParseResult<V::Value> {
+        match self.peek()? {
+            // a member name when the enum is a map key
+            Some(Token::String) | Some(Token::PropertyName) => visitor.visit_enum(EnumAccess { de: self, tagged: false }),
+            Some(Token::ObjectStart) => {
+                self.peeked = None;
+                self.descend()?;
+                let value = visitor.visit_enum(EnumAccess { de: self, tagged: true })?;
+                self.depth_left += 1;
+                match self.next()? {
+                    Token::ObjectEnd => Ok(value),
+                    _ => Err(ParseErr::BadData(String::from("Expected just the one member, the variant, in the enum object"))),
+                }
+            },
+            _ => Err(ParseErr::BadData(String::from("Expected an enum, either a string or an object with one member"))),
+        }
+    }
+
+    /// Skips the value, the whole of an array or object is passed over without
+    /// being parsed
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        match self.peeked.take() {
+            Some(Token::ArrayStart) | Some(Token::ObjectStart) => self.parser.skip_children()?,
+            Some(_) => (),
+            None => { self.parser.skip_value()?; },
+        }
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+
+/// Elements of an array, up to its end, which is left for `deserialize_any`
+struct SeqAccess<'a, 'de, R: DeInput<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+impl <'de, 'a, R: DeInput<'de>> de::SeqAccess<'de> for SeqAccess<'a, 'de, R> {
+    type Error = ParseErr;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> ParseResult<Option<T::Value>> {
+        match self.de.peek()? {
+            Some(Token::ArrayEnd) => Ok(None),
+            _ => seed.deserialize(&mut *self.de).map(Some),
+        }
+    }
+}
+
+
+/// Members of an object, up to its end, which is left for `deserialize_any`
+struct MapAccess<'a, 'de, R: DeInput<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+impl <'de, 'a, R: DeInput<'de>> de::MapAccess<'de> for MapAccess<'a, 'de, R> {
+    type Error = ParseErr;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> ParseResult<Option<K::Value>> {
+        match self.de.peek()? {
+            Some(Token::ObjectEnd) => Ok(None),
+            _ => seed.deserialize(MapKey { de: &mut *self.de }).map(Some),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> ParseResult<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+
+/// A member name, which can also be taken as a number for maps with number keys
+struct MapKey<'a, 'de, R: DeInput<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+}
+
+/// Integer keys, parsed from the member name
+macro_rules! deserialize_key_integer {
+    ($( $method:ident $visit:ident $t:ty ),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+                self.de.next()?;
+                let name = self.de.parser.token_text();
+                match name.parse::<$t>() {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(name), &visitor)),
+                }
+            }
+        )*
+    }
+}
+
+impl <'de, 'a, R: DeInput<'de>> de::Deserializer<'de> for MapKey<'a, 'de, R> {
+    type Error = ParseErr;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.de.deserialize_any(visitor)
+    }
+
+    deserialize_key_integer! {
+        deserialize_i8 visit_i8 i8, deserialize_i16 visit_i16 i16, deserialize_i32 visit_i32 i32,
+        deserialize_i64 visit_i64 i64, deserialize_u8 visit_u8 u8, deserialize_u16 visit_u16 u16,
+        deserialize_u32 visit_u32 u32, deserialize_u64 visit_u64 u64
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> ParseResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+            visitor: V) -> ParseResult<V::Value> {
+        self.de.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> ParseResult<V::Value> {
+        self.de.deserialize_ignored_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+
+/// The variant of an enum, from a string or the one member of an object
+struct EnumAccess<'a, 'de, R: DeInput<'de>> {
+    de: &'a mut Deserializer<'de, R>,
+    /// In an object, with the variant's name as the member name
+    tagged: bool,
+}
+
+impl <'de, 'a, R: DeInput<'de>> de::EnumAccess<'de> for EnumAccess<'a, 'de, R> {
+    type Error = ParseErr;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> ParseResult<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl <'de, 'a, R: DeInput<'de>> de::VariantAccess<'de> for EnumAccess<'a, 'de, R> {
+    type Error = ParseErr;
+
+    fn unit_variant(self) -> ParseResult<()> {
+        match self.tagged {
+            // as an object, the value is null
+            true => <()>::deserialize(self.de),
+            false => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> ParseResult<T::Value> {
+        match self.tagged {
+            true => seed.deserialize(self.de),
+            false => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> ParseResult<V::Value> {
+        match self.tagged {
+            true => de::Deserializer::deserialize_seq(self.de, visitor),
+            false => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> ParseResult<V::Value> {
+        match self.tagged {
+            true => de::Deserializer::deserialize_map(self.de, visitor),
+            false => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"struct variant")),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde::Deserialize;
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashMap};
+    use std::io::Cursor;
+    use crate::json2::JsonParserBuilder;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect(u32, u32),
+        Poly { sides: u8, name: Option<String> },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Line<'a> {
+        sku: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+        qty: u32,
+        price: f64,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Order<'a> {
+        id: u64,
+        #[serde(borrow)]
+        lines: Vec<Line<'a>>,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, i64>,
+        counts: HashMap<u32, bool>,
+        paid: Option<bool>,
+        pair: (i8, String),
+        #[serde(default)]
+        missing: Vec<u8>,
+    }
+
+    const ORDER: &str = r#"{
+        "id": 18446744073709551615,
+        "lines": [
+            {"sku": "A-1", "note": "plain", "qty": 2, "price": 9.5},
+            {"sku": "B-2", "unknown": {"deep": [1, {"x": "]}"}]}, "note": "has \"quotes\"", "qty": 0, "price": -1e2}
+        ],
+        "shapes": ["Point", {"Circle": 1.5}, {"Rect": [2, 3]}, {"Poly": {"sides": 6, "name": null}}],
+        "tags": {"b": -9223372036854775808, "a": 1},
+        "counts": {"7": true, "12": false},
+        "paid": null,
+        "pair": [-5, "five"],
+        "ignored": [[], {}, "x", 1.0, true, null]
+    }"#;
+
+    #[test]
+    fn test_from_slice() -> ParseResult<()> {
+        let order: Order<'_> = from_str(ORDER)?;
+        assert_eq!(u64::MAX, order.id);
+        assert_eq!(Line { sku: "A-1", note: Cow::Borrowed("plain"), qty: 2, price: 9.5 }, order.lines[0]);
+        assert_eq!("has \"quotes\"", order.lines[1].note);
+        assert_eq!(-100.0, order.lines[1].price);
+        assert_eq!(vec![Shape::Point, Shape::Circle(1.5), Shape::Rect(2, 3), Shape::Poly { sides: 6, name: None }], order.shapes);
+        assert_eq!(vec![("a", 1), ("b", i64::MIN)], order.tags.iter().map(|(k, v)| (k.as_str(), *v)).collect::<Vec<_>>());
+        assert_eq!(Some(&true), order.counts.get(&7));
+        assert_eq!(None, order.paid);
+        assert_eq!((-5, String::from("five")), order.pair);
+        assert!(order.missing.is_empty());
+        assert_eq!(u128::MAX, from_str::<u128>("340282366920938463463374607431768211455")?);
+
+        // both 128 bit types take whole numbers written with an exponent, and fail the same way
+        assert_eq!(1000, from_str::<i128>("1e3")?);
+        assert_eq!(1000, from_str::<u128>("1e3")?);
+        assert_eq!(from_str::<i128>("1.5").unwrap_err().to_string().replace("i128", "u128"),
+            from_str::<u128>("1.5").unwrap_err().to_string());
+        assert!(from_str::<u128>("-1").is_err());
+
+        // strings without escapes are borrowed from the input
+        assert!(matches!(order.lines[0].note, Cow::Borrowed(_)));
+        assert!(matches!(order.lines[1].note, Cow::Owned(_)));
+        let input = ORDER.as_bytes().as_ptr_range();
+        assert!(input.contains(&order.lines[1].sku.as_ptr()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_reader() -> ParseResult<()> {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Owned { name: String, values: Vec<Option<i128>>, unit: (), shape: Shape }
+
+        let text = r#"{"name": "aéb", "values": [1, null, -170141183460469231731687303715884105728],
+            "unit": null, "shape": {"Poly": {"name": "hex", "sides": 6}}}"#;
+        let expected = Owned {
+            name: String::from("aéb"), values: vec![Some(1), None, Some(i128::MIN)], unit: (),
+            shape: Shape::Poly { sides: 6, name: Some(String::from("hex")) },
+        };
+        // small buffer, so tokens straddle refills
+        let mut de = Deserializer::new(JsonParserBuilder::new().buffer_size(5).build(Cursor::new(text)));
+        assert_eq!(expected, de.parse()?);
+        de.end()?;
+        assert_eq!(expected, from_reader(Cursor::new(text))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_values() -> ParseResult<()> {
+        let p = JsonParserBuilder::new().multiple_values(true).build(SliceInput::new(b"[1, 2] [] [3]"));
+        let mut de = Deserializer::new(p);
+        assert_eq!(vec![1, 2], de.parse::<Vec<u8>>()?);
+        assert_eq!(Vec::<u8>::new(), de.parse::<Vec<u8>>()?);
+        assert_eq!((3,), de.parse::<(u8,)>()?);
+        de.end()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        // where in the document it went wrong
+        let e = from_str::<Order<'_>>(&ORDER.replace("\"qty\": 0", "\"qty\": -1")).unwrap_err();
+        assert_eq!(Some("$.lines[1].qty"), e.path());
+        assert!(e.to_string().starts_with("invalid value: integer `-1`, expected u32 at line 5,"), "{}", e);
+
+        // an escaped string can't be borrowed
+        let e = from_str::<Order<'_>>(&ORDER.replace("A-1", "A\\n1")).unwrap_err();
+        assert!(e.to_string().contains("expected a borrowed string"), "{}", e);
+
+        let e = from_str::<Line<'_>>(r#"{"sku": "a", "qty": 1, "price": 2}"#).unwrap_err();
+        assert!(e.to_string().starts_with("missing field `note`"), "{}", e);
+
+        assert!(from_str::<(u8, u8)>("[1, 2, 3]").is_err());
+        assert!(from_str::<Shape>(r#"{"Circle": 1, "Point": null}"#).is_err());
+        assert!(from_str::<Shape>(r#""Circle""#).is_err());
+        assert!(from_str::<Shape>("[]").is_err());
+        assert!(from_str::<HashMap<u8, u8>>(r#"{"300": 1}"#).is_err());
+
+        let e = from_str::<Vec<u8>>("[1] 2").unwrap_err();
+        assert_eq!(Some(&ErrorKind::TrailingData), e.kind());
+        let e = from_str::<Vec<u8>>("[1, ").unwrap_err();
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), e.kind());
+    }
+
+    #[test]
+    fn test_max_depth() -> ParseResult<()> {
+        #[derive(Debug, Deserialize)]
+        struct Nested(Vec<Nested>);
+
+        #[derive(Debug, Deserialize)]
+        enum Chain {
+            End,
+            Next(Box<Chain>),
+        }
+
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(1, from_str::<Nested>(&nested(128))?.0.len());
+        let e = from_str::<Nested>(&nested(129)).unwrap_err();
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+        assert_eq!(Some(129), e.position().map(|p| p.offset));
+
+        // stops well before the call stack runs out
+        let e = from_reader::<_, Nested>(Cursor::new(nested(1_000_000))).unwrap_err();
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+
+        // tagged enums count too
+        let chain = |depth| format!("{}\"End\"{}", "{\"Next\":".repeat(depth), "}".repeat(depth));
+        assert!(matches!(from_str::<Chain>(&chain(128))?, Chain::Next(c) if matches!(*c, Chain::Next(_))));
+        let e = from_str::<Chain>(&chain(129)).unwrap_err();
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+
+        let text = nested(200);
+        let mut de = Deserializer::new(JsonParser::from_slice(text.as_bytes())).max_depth(200);
+        de.parse::<Nested>()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+//! Newline delimited JSON (also known as JSON lines), where each line is a record
+//! holding one value.  Blank lines are skipped
+
+use crate::internals::{ParseResult, ParseErr, ErrorKind, Limit};
+use crate::json2::{JsonParser, JsonEvent2, JsonInput};
+use crate::json_value::JsonValue;
+
+
+/// Where we are in the input
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineState {
+    /// Before the first record, or between records
+    Between,
+    /// Part way through a record's value
+    InRecord,
+    /// A record's value is complete, the rest of its line is next
+    RecordDone,
+    /// A record had an error, the rest of its line is skipped before the next one
+    Failed,
+    /// Reading the input failed, so there is nothing more
+    Ended,
+}
+
+
+/// Reads records one at a time, either as events with `next_record` and `next_token`,
+/// or as a `JsonValue` per record by iterating.  Errors say which record they are in,
+/// and parsing carries on with the next record after an error.
+/// The one parser, and its buffer, is used for all the records
+pub struct JsonLines<R: JsonInput> {
+    parser: JsonParser<R>,
+
+    /// Current record, counting from 1
+    record: usize,
+
+    state: LineState,
+}
+
+
+impl <R: JsonInput> JsonLines<R> {
+
+    pub fn new(r: R) -> JsonLines<R> {
+        Self::from_parser(JsonParser::new(r))
+    }
+
+    /// Reads records with a parser that has been set up already
+    pub fn from_parser(p: JsonParser<R>) -> JsonLines<R> {
+        JsonLines {
+            parser: p.line_delimited(),
+            record: 0,
+            state: LineState::Between,
+        }
+    }
+
+    /// Number of the current record, counting from 1.  Blank lines are not counted
+    pub fn record_number(&self) -> usize {
+        self.record
+    }
+
+    /// Moves on to the next record, returning false at the end of the input.
+    /// Anything left of the current record is skipped, including after an error
+    pub fn next_record(&mut self) -> ParseResult<bool> {
+        while self.state == LineState::InRecord {
+            self.next_token()?;
+        }
+        match self.state {
+            LineState::RecordDone => {
+                if let Err(e) = self.parser.end_line() {
+                    return Err(Self::record_err(&mut self.state, self.record, e));
+                }
+            },
+            LineState::Failed => self.parser.skip_line().map_err(|e| Self::stop(&mut self.state, self.parser.locate(e)))?,
+            LineState::Ended => return Ok(false),
+            _ => {},
+        }
+
+        self.state = LineState::Between;
+        if self.parser.at_end().map_err(|e| Self::stop(&mut self.state, self.parser.locate(e)))? {
+            return Ok(false);
+        }
+        self.record += 1;
+        self.state = LineState::InRecord;
+        Ok(true)
+    }
+
+    /// Next event of the current record, `None` once the record is complete
+    pub fn next_token(&mut self) -> ParseResult<Option<JsonEvent2<'_>>> {
+        if self.state != LineState::InRecord {
+            return Ok(None);
+        }
+        let depth = self.parser.depth();
+        match self.parser.next_token() {
+            Ok(Some(e)) => {
+                // complete once a value takes us back to the top level
+                let depth = match e {
+                    JsonEvent2::ObjectStart | JsonEvent2::ArrayStart => depth + 1,
+                    JsonEvent2::ObjectEnd | JsonEvent2::ArrayEnd => depth - 1,
+                    _ => depth,
+                };
+                if depth == 0 && !e.is_property_name() {
+                    self.state = LineState::RecordDone;
+                }
+                Ok(Some(e))
+            },
+            Ok(None) => {
+                self.state = LineState::RecordDone;
+                Ok(None)
+            },
+            Err(e) => Err(Self::record_err(&mut self.state, self.record, e)),
+        }
+    }
+
+    /// The current record as a value, which must be all there is on its line
+    fn record_value(&mut self) -> ParseResult<JsonValue> {
+        let v = JsonValue::from_parser(&mut self.parser)
+            .and_then(|v| self.parser.end_line().map(|_| v));
+        match v {
+            Ok(v) => {
+                self.state = LineState::Between;
+                Ok(v)
+            },
+            Err(e) => Err(Self::record_err(&mut self.state, self.record, e)),
+        }
+    }
+
+    /// Error in the current record, which will be skipped.  Takes the fields rather
+    /// than `self`, so it can be used while the parser is borrowed
+    fn record_err(state: &mut LineState, record: usize, e: ParseErr) -> ParseErr {
+        let e = Self::stop(state, e);
+        if *state != LineState::Ended {
+            *state = LineState::Failed;
+        }
+        ParseErr::InRecord(record, Box::new(e))
+    }
+
+    /// We can't carry on after I/O errors, rather than keep trying, or once the
+    /// input is over its size limit
+    fn stop(state: &mut LineState, e: ParseErr) -> ParseErr {
+        match (e.inner(), e.kind()) {
+            (ParseErr::Io(_), _) | (_, Some(ErrorKind::LimitExceeded { limit: Limit::DocumentSize, .. })) =>
+                *state = LineState::Ended,
+            _ => {},
+        }
+        e
+    }
+}
+
+
+/// Each record as a value.  After an error, iterating carries on with the next record
+impl <R: JsonInput> Iterator for JsonLines<R> {
+    type Item = ParseResult<JsonValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_record() {
+            Ok(true) => Some(self.record_value()),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::json2::JsonParserBuilder;
+    use std::io::Cursor;
+
+    fn lines(s: &str) -> JsonLines<Cursor<&[u8]>> {
+        JsonLines::new(Cursor::new(s.as_bytes()))
+    }
+
+    #[test]
+    fn test_values() -> ParseResult<()> {
+        let text = "{\"a\": 1}\n[1, 2]\r\n\n   \n\"three\"  \n4\nnull";
+        let values = lines(text).collect::<ParseResult<Vec<JsonValue>>>()?;
+        let written: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["{\"a\":1}", "[1,2]", "\"three\"", "4", "null"], written);
+
+        assert_eq!(0, lines("").count());
+        assert_eq!(0, lines("\n \n").count());
+        Ok(())
+    }
+
+    #[test]
+    fn test_events() -> ParseResult<()> {
+        let mut l = lines("{\"a\": [1, 2]}\n[true]\n\"x\"\n");
+
+        assert!(l.next_record()?);
+        assert_eq!(1, l.record_number());
+        assert!(l.next_token()?.unwrap().is_obj_start());
+        assert!(l.next_token()?.unwrap().is_property_name());
+
+        // the rest of the record is skipped
+        assert!(l.next_record()?);
+        assert_eq!(2, l.record_number());
+        assert!(l.next_token()?.unwrap().is_arr_start());
+        assert!(l.next_token()?.unwrap().is_bool());
+        assert!(l.next_token()?.unwrap().is_arr_end());
+        assert!(l.next_token()?.is_none());
+        assert!(l.next_token()?.is_none());
+
+        assert!(l.next_record()?);
+        assert!(matches!(l.next_token()?, Some(JsonEvent2::String("x"))));
+        assert!(l.next_token()?.is_none());
+        assert!(!l.next_record()?);
+        assert!(!l.next_record()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_carry_on() {
+        let text = "1\n[1,,]\n{\"ok\": true}\n2 3\n\n\"end\"\n";
+        let results: Vec<ParseResult<JsonValue>> = lines(text).collect();
+        assert_eq!(5, results.len());
+
+        assert!(results[0].is_ok());
+        let e = results[1].as_ref().unwrap_err();
+        assert_eq!(Some(2), e.record());
+        assert_eq!(Some(2), e.position().map(|p| p.line));
+        assert!(matches!(e.kind(), Some(ErrorKind::UnexpectedByte { found: b',', .. })));
+        assert_eq!(Some("Unexpected ',', expected a value at line 2, column 4 in $[1] in record 2"),
+            results[1].as_ref().err().map(|e| e.to_string()).as_deref());
+
+        assert_eq!(Some(true), results[2].as_ref().ok().and_then(|v| v["ok"].as_bool()));
+
+        let e = results[3].as_ref().unwrap_err();
+        assert_eq!(Some(4), e.record());
+        assert_eq!(Some(&ErrorKind::TrailingData), e.kind());
+
+        assert_eq!(Some("end"), results[4].as_ref().ok().and_then(|v| v.as_str()));
+    }
+
+    #[test]
+    fn test_records_end_at_newline() {
+        // the record missing its end doesn't take the next one with it
+        let results: Vec<ParseResult<JsonValue>> = lines("{\"a\":1\n{\"b\":2}\n{\"c\":3}\n").collect();
+        assert_eq!(3, results.len());
+        let e = results[0].as_ref().unwrap_err();
+        assert_eq!(Some(1), e.record());
+        assert_eq!(Some(1), e.position().map(|p| p.line));
+        assert_eq!(Some(2), results[1].as_ref().ok().and_then(|v| v["b"].as_number()).map(|n| n.as_i64().unwrap()));
+        assert_eq!(Some(3), results[2].as_ref().ok().and_then(|v| v["c"].as_number()).map(|n| n.as_i64().unwrap()));
+
+        // or a string missing its end
+        let results: Vec<ParseResult<JsonValue>> = lines("{\"a\":\"x\n{\"b\":2}\n").collect();
+        assert_eq!(2, results.len());
+        assert!(results[1].is_ok());
+
+        // nor is a value split over lines one record
+        let results: Vec<ParseResult<JsonValue>> = lines("[1,\n2]\n[3]").collect();
+        assert_eq!(3, results.len());
+        assert_eq!(Some(1), results[0].as_ref().unwrap_err().record());
+        assert_eq!(Some(2), results[1].as_ref().unwrap_err().record());
+        assert_eq!(Some("[3]"), results[2].as_ref().ok().map(|v| v.to_string()).as_deref());
+
+        // same for events, and whitespace either side of a record is still fine
+        let mut l = lines(" [1,\n 2 ] \r\n\t\n \"x\" ");
+        assert!(l.next_record().unwrap());
+        assert!(l.next_token().unwrap().unwrap().is_arr_start());
+        assert!(l.next_token().unwrap().unwrap().is_number());
+        assert!(l.next_token().is_err());
+        assert!(l.next_record().unwrap());
+        assert!(l.next_token().unwrap().unwrap().is_number());
+        assert_eq!(Some(&ErrorKind::TrailingData), l.next_record().unwrap_err().kind());
+        assert!(l.next_record().unwrap());
+        assert_eq!(3, l.record_number());
+        assert!(matches!(l.next_token(), Ok(Some(JsonEvent2::String("x")))));
+        assert!(!l.next_record().unwrap());
+    }
+
+    #[test]
+    fn test_document_size() {
+        // records before the limit are fine, then the error ends it
+        let p = JsonParserBuilder::new().buffer_size(4).max_document_size(9).build(Cursor::new(b"1\n2\n[3]\n[4]\n5\n".to_vec()));
+        let results: Vec<ParseResult<JsonValue>> = JsonLines::from_parser(p).collect();
+        assert_eq!(4, results.len());
+        assert_eq!(Some("[3]"), results[2].as_ref().ok().map(|v| v.to_string()).as_deref());
+        let e = results[3].as_ref().unwrap_err();
+        assert_eq!(Some(&ErrorKind::LimitExceeded { limit: Limit::DocumentSize, max: 9 }), e.kind());
+        assert_eq!(Some(9), e.position().map(|p| p.offset));
+    }
+
+    #[test]
+    fn test_many_records() {
+        // enough to need several re-fills of the buffer
+        let mut text = String::new();
+        for i in 0..3000 {
+            if i == 1500 {
+                text.push_str("{\"bad\": \"unterminated}\n");
+            } else {
+                text.push_str(&format!("{{\"n\": {}, \"s\": \"record {}\"}}\n", i, i));
+            }
+        }
+        let mut ok = 0;
+        for (i, r) in lines(&text).enumerate() {
+            match r {
+                Ok(v) => {
+                    assert_eq!(i as i64, v["n"].as_number().unwrap().as_i64().unwrap());
+                    ok += 1;
+                },
+                Err(e) => assert_eq!(Some(1501), e.record()),
+            }
+        }
+        assert_eq!(2999, ok);
+    }
+}
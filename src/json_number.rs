@@ -0,0 +1,376 @@
+//! Json numbers, kept as the text from the input until the client asks for a type
+
+use std::fmt;
+use crate::internals::{ParseResult, ParseErr, ErrorKind};
+
+/// A number from the JSON input.
+/// The text is kept along with where the integer, fraction and exponent parts are,
+/// as found when the number was scanned, so conversions don't need to look for them again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JsonNumber<'a> {
+    text: &'a str,
+
+    /// End of the integer digits (after any minus)
+    int_end: usize,
+
+    /// End of the fraction, including the period.  Same as `int_end` if there is no fraction
+    frac_end: usize,
+}
+
+
+/// An exact decimal, the value is `digits * 10^exponent`, negated if `negative`.
+/// Useful where a float would lose precision, e.g. money
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonDecimal {
+    pub negative: bool,
+
+    /// Significant digits, without leading zeros.  "0" for zero
+    pub digits: String,
+
+    pub exponent: i64,
+}
+
+
+/// Digits that will always fit in a u64 without checking for overflow
+const SAFE_U64_DIGITS: usize = 19;
+
+
+impl <'a> JsonNumber<'a> {
+
+    /// Used by the parser, which has already checked the grammar and knows where the parts are
+    pub(crate) fn from_parts(text: &'a str, int_end: usize, frac_end: usize) -> JsonNumber<'a> {
+        JsonNumber { text, int_end, frac_end }
+    }
+
+    /// Checks `text` is a JSON number, finding where the parts are
+    pub fn parse(text: &'a str) -> ParseResult<JsonNumber<'a>> {
+        let b = text.as_bytes();
+        let digits = |from: usize| from + b[from..].iter().take_while(|c| c.is_ascii_digit()).count();
+
+        let int_start = if b.first() == Some(&b'-') { 1 } else { 0 };
+        let int_end = digits(int_start);
+        if int_end == int_start || (b[int_start] == b'0' && int_end - int_start > 1) {
+            return Err(Self::bad_number());
+        }
+
+        let mut frac_end = int_end;
+        if b.get(frac_end) == Some(&b'.') {
+            frac_end = digits(int_end + 1);
+            if frac_end == int_end + 1 {
+                return Err(Self::bad_number());
+            }
+        }
+
+        let mut end = frac_end;
+        if let Some(b'e') | Some(b'E') = b.get(end) {
+            end += 1;
+            if let Some(b'+') | Some(b'-') = b.get(end) {
+                end += 1;
+            }
+            let exp_end = digits(end);
+            if exp_end == end {
+                return Err(Self::bad_number());
+            }
+            end = exp_end;
+        }
+
+        if end != b.len() {
+            return Err(Self::bad_number());
+        }
+        Ok(JsonNumber { text, int_end, frac_end })
+    }
+
+    fn bad_number() -> ParseErr {
+        ErrorKind::InvalidNumber("not a JSON number").into()
+    }
+
+    fn overflow(&self, type_name: &str) -> ParseErr {
+        ParseErr::BadData(format!("Number {} does not fit in {}", self.text, type_name))
+    }
+
+    /// The number as it appeared in the input
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.text.starts_with('-')
+    }
+
+    /// True if written without a fraction or exponent
+    pub fn is_integer(&self) -> bool {
+        self.int_end == self.text.len()
+    }
+
+    /// The integer digits, without the sign
+    fn int_digits(&self) -> &'a str {
+        let start = if self.is_negative() { 1 } else { 0 };
+        &self.text[start..self.int_end]
+    }
+
+    /// The fraction digits, without the period
+    fn frac_digits(&self) -> &'a str {
+        if self.frac_end > self.int_end {
+            &self.text[self.int_end + 1..self.frac_end]
+        } else {
+            ""
+        }
+    }
+
+    /// The exponent text, with any sign
+    fn exp_digits(&self) -> &'a str {
+        if self.frac_end < self.text.len() {
+            &self.text[self.frac_end + 1..]
+        } else {
+            ""
+        }
+    }
+
+    /// Magnitude of the number as an integer, with the number of digits checked.
+    /// Anything with a fraction must have exponent enough to make it whole
+    fn magnitude(&self, type_name: &str) -> ParseResult<u128> {
+        if self.is_integer() {
+            let digits = self.int_digits();
+            if digits.len() <= SAFE_U64_DIGITS {
+                // can't overflow, no need to check each step
+                return Ok(digits.bytes().fold(0u64, |n, c| n * 10 + (c - b'0') as u64) as u128);
+            }
+            return Self::accumulate(digits.bytes(), 0).ok_or_else(|| self.overflow(type_name));
+        }
+
+        let d = self.decimal()?;
+        if d.digits == "0" {
+            return Ok(0);
+        }
+        if d.exponent < 0 {
+            return Err(ParseErr::BadData(format!("Number {} is not an integer", self.text)));
+        }
+        // 39 digits is past the largest u128
+        if (d.digits.len() as i64).saturating_add(d.exponent) > 39 {
+            return Err(self.overflow(type_name));
+        }
+        let n = Self::accumulate(d.digits.bytes(), 0).ok_or_else(|| self.overflow(type_name))?;
+        Self::accumulate(std::iter::repeat(b'0').take(d.exponent as usize), n).ok_or_else(|| self.overflow(type_name))
+    }
+
+    /// Adds decimal digits on to `n`, checking for overflow
+    fn accumulate<I: Iterator<Item=u8>>(digits: I, n: u128) -> Option<u128> {
+        let mut n = n;
+        for c in digits {
+            n = n.checked_mul(10)?.checked_add((c - b'0') as u128)?;
+        }
+        Some(n)
+    }
+
+    pub fn as_u64(&self) -> ParseResult<u64> {
+        let n = self.as_u128_named("u64")?;
+        if n > u64::MAX as u128 {
+            return Err(self.overflow("u64"));
+        }
+        Ok(n as u64)
+    }
+
+    pub fn as_u128(&self) -> ParseResult<u128> {
+        self.as_u128_named("u128")
+    }
+
+    fn as_u128_named(&self, type_name: &str) -> ParseResult<u128> {
+        let n = self.magnitude(type_name)?;
+        if self.is_negative() && n != 0 {
+            return Err(self.overflow(type_name));
+        }
+        Ok(n)
+    }
+
+    pub fn as_i64(&self) -> ParseResult<i64> {
+        let n = self.as_i128_named("i64")?;
+        if n < i64::MIN as i128 || n > i64::MAX as i128 {
+            return Err(self.overflow("i64"));
+        }
+        Ok(n as i64)
+    }
+
+    pub fn as_i128(&self) -> ParseResult<i128> {
+        self.as_i128_named("i128")
+    }
+
+    fn as_i128_named(&self, type_name: &str) -> ParseResult<i128> {
+        let n = self.magnitude(type_name)?;
+        if self.is_negative() {
+            // the magnitude of i128::MIN is one more than i128::MAX
+            if n > i128::MAX as u128 + 1 {
+                return Err(self.overflow(type_name));
+            }
+            Ok((n as i128).wrapping_neg())
+        } else {
+            if n > i128::MAX as u128 {
+                return Err(self.overflow(type_name));
+            }
+            Ok(n as i128)
+        }
+    }
+
+    /// Nearest f64 to the number, numbers too large for an f64 are an error
+    /// rather than infinity.  Very small numbers go to zero
+    pub fn as_f64(&self) -> ParseResult<f64> {
+        let int_digits = self.int_digits();
+        if self.is_integer() && int_digits.len() < 16 {
+            // exact in an f64, no need for the general conversion
+            let n = int_digits.bytes().fold(0u64, |n, c| n * 10 + (c - b'0') as u64) as f64;
+            return Ok(if self.is_negative() { -n } else { n });
+        }
+        match self.text.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(self.overflow("f64")),
+        }
+    }
+
+    /// The exact value of the number, as sign, digits and exponent
+    pub fn decimal(&self) -> ParseResult<JsonDecimal> {
+        let frac = self.frac_digits();
+        let mut digits = String::with_capacity(self.int_end + frac.len());
+        digits.push_str(self.int_digits());
+        digits.push_str(frac);
+
+        // normalise, no leading zeros and no trailing zeros
+        let lead = digits.bytes().take_while(|c| *c == b'0').count();
+        digits.drain(..lead);
+        let trail = digits.bytes().rev().take_while(|c| *c == b'0').count();
+        digits.truncate(digits.len() - trail);
+
+        if digits.is_empty() {
+            return Ok(JsonDecimal { negative: self.is_negative(), digits: String::from("0"), exponent: 0 });
+        }
+
+        let exp = self.exp_digits();
+        let exponent = if exp.is_empty() {
+            0
+        } else {
+            exp.trim_start_matches('+').parse::<i64>()
+                .map_err(|_| self.overflow("a decimal exponent"))?
+        };
+
+        let exponent = exponent.checked_sub(frac.len() as i64)
+            .and_then(|e| e.checked_add(trail as i64))
+            .ok_or_else(|| self.overflow("a decimal exponent"))?;
+
+        Ok(JsonDecimal { negative: self.is_negative(), digits, exponent })
+    }
+}
+
+
+impl <'a> fmt::Display for JsonNumber<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+
+impl <'a> PartialEq<&str> for JsonNumber<'a> {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn num(s: &str) -> JsonNumber<'_> {
+        JsonNumber::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        for s in &["0", "-0", "12", "-1.5", "1e5", "1.25E-3", "0.0e+0"] {
+            assert!(JsonNumber::parse(s).is_ok(), "{}", s);
+        }
+        for s in &["", "-", "01", "1.", ".1", "1e", "1e+", "1x", " 1", "+1"] {
+            assert!(JsonNumber::parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_integers() -> ParseResult<()> {
+        assert_eq!(0, num("0").as_i64()?);
+        assert_eq!(-42, num("-42").as_i64()?);
+        assert_eq!(i64::MAX, num("9223372036854775807").as_i64()?);
+        assert_eq!(i64::MIN, num("-9223372036854775808").as_i64()?);
+        assert!(num("9223372036854775808").as_i64().is_err());
+        assert!(num("-9223372036854775809").as_i64().is_err());
+
+        assert_eq!(u64::MAX, num("18446744073709551615").as_u64()?);
+        assert!(num("18446744073709551616").as_u64().is_err());
+        assert!(num("-1").as_u64().is_err());
+        assert_eq!(0, num("-0").as_u64()?);
+
+        assert_eq!(u128::MAX, num("340282366920938463463374607431768211455").as_u128()?);
+        assert!(num("340282366920938463463374607431768211456").as_u128().is_err());
+        assert!(num("-1").as_u128().is_err());
+
+        assert_eq!(i128::MIN, num("-170141183460469231731687303715884105728").as_i128()?);
+        assert_eq!(i128::MAX, num("170141183460469231731687303715884105727").as_i128()?);
+        assert!(num("170141183460469231731687303715884105728").as_i128().is_err());
+        assert!(num("1000000000000000000000000000000000000000000").as_i128().is_err());
+
+        // whole numbers written with a fraction or exponent
+        assert_eq!(100, num("1e2").as_i64()?);
+        assert_eq!(100, num("1e2").as_u128()?);
+        assert_eq!(1500, num("1.5E3").as_i64()?);
+        assert_eq!(-2, num("-2.000").as_i64()?);
+        assert_eq!(0, num("0.0e10").as_i64()?);
+        assert!(num("1.5").as_i64().is_err());
+        assert!(num("1e-2").as_i64().is_err());
+        assert!(num("1e40").as_i128().is_err());
+        assert!(num("1e100000000000000000000").as_i64().is_err());
+
+        // exponents at the ends of i64 are too big, or not whole, rather than overflowing
+        for big in &["1e9223372036854775807", "-1e9223372036854775807", "12e9223372036854775807",
+                "10e9223372036854775807", "1e-9223372036854775808", "0.1e-9223372036854775808"] {
+            assert!(num(big).as_i64().is_err(), "{}", big);
+            assert!(num(big).as_u64().is_err(), "{}", big);
+            assert!(num(big).as_i128().is_err(), "{}", big);
+            assert!(num(big).as_u128().is_err(), "{}", big);
+        }
+        assert_eq!(0, num("0e9223372036854775807").as_i64()?);
+
+        assert!(num("12").is_integer());
+        assert!(!num("1e2").is_integer());
+        assert!(!num("1.0").is_integer());
+        Ok(())
+    }
+
+    #[test]
+    fn test_floats() -> ParseResult<()> {
+        assert_eq!(0.0, num("0").as_f64()?);
+        assert_eq!(-12.5, num("-12.5").as_f64()?);
+        assert_eq!(2.5e-3, num("2.5E-3").as_f64()?);
+        assert_eq!(1e300, num("1e300").as_f64()?);
+        assert_eq!(9007199254740993f64, num("9007199254740993").as_f64()?);
+        assert_eq!(0.0, num("1e-400").as_f64()?);
+        assert!(num("1e400").as_f64().is_err());
+        assert!(num("-1e400").as_f64().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal() -> ParseResult<()> {
+        let d = num("-12.340").decimal()?;
+        assert_eq!(JsonDecimal { negative: true, digits: String::from("1234"), exponent: -2 }, d);
+
+        let d = num("0.001e5").decimal()?;
+        assert_eq!(JsonDecimal { negative: false, digits: String::from("1"), exponent: 2 }, d);
+
+        let d = num("1200").decimal()?;
+        assert_eq!(JsonDecimal { negative: false, digits: String::from("12"), exponent: 2 }, d);
+
+        let d = num("0.000").decimal()?;
+        assert_eq!(JsonDecimal { negative: false, digits: String::from("0"), exponent: 0 }, d);
+
+        let d = num("12345678901234567890.123456789").decimal()?;
+        assert_eq!(JsonDecimal { negative: false, digits: String::from("12345678901234567890123456789"), exponent: -9 }, d);
+        Ok(())
+    }
+}
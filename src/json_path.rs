@@ -0,0 +1,899 @@
+//! JSONPath queries, such as `$.orders[*].items[?(@.qty > 2)].sku`, over a built
+//! document or over the events of a `JsonParser` as they are parsed.
+//!
+//! Supported are member names (`.name` or `['name']`), indexes (`[3]`, `[-1]`), wildcards
+//! (`*`), recursive descent (`..`), unions (`[0, 'a']`), slices (`[start:end:step]`) and
+//! filters (`[?(@.price < 10 && @.tags)]`).  A filter compares values with `==`, `!=`, `<`,
+//! `<=`, `>` or `>=`, or tests a member is there, and can be combined with `&&`, `||`,
+//! `!` and brackets.
+//!
+//! Matches come in document order, each only once, so they can be handed out as they
+//! are parsed.  This differs from RFC 9535, where a union gives its matches in the order
+//! of its selectors and a slice with a negative step goes backwards: here `$[::-1]`
+//! and `$[2, 0]` both match from the first element to the last.
+//!
+//! The path is parsed with the combinators of `internals::parsers`
+
+use std::collections::VecDeque;
+use std::io::{Cursor, Read};
+use crate::internals::{ParseResult, ParseErr, RewindableChars};
+use crate::internals::parsers;
+use crate::json2::{JsonParser, JsonEvent2, JsonInput};
+use crate::json_number::JsonNumber;
+use crate::json_value::JsonValue;
+
+
+/// A parsed JSONPath
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+
+/// One step of the path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Children that match any of the selectors
+    Child(Vec<Selector>),
+    /// Children of this or any value inside it that match any of the selectors
+    Descendant(Vec<Selector>),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    /// Counting back from the end if negative
+    Index(i64),
+    Wildcard,
+    /// Start, end and step
+    Slice(Option<i64>, Option<i64>, i64),
+    Filter(Filter),
+}
+
+
+/// Test for a child, which is `@` in the path
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Or(Box<Filter>, Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    /// The value in the child is there
+    Exists(Vec<PathKey>),
+    Compare(Operand, CmpOp, Operand),
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// Value in the child, which may not be there
+    Path(Vec<PathKey>),
+    Value(JsonValue),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+
+/// Step of a path in a filter
+#[derive(Debug, Clone, PartialEq)]
+enum PathKey {
+    Name(String),
+    Index(i64),
+}
+
+
+/// Which child of an array or object
+#[derive(Debug, Clone, Copy)]
+enum Key<'k> {
+    Name(&'k str),
+    Index(usize),
+}
+
+
+impl Selector {
+
+    /// Whether the child is selected.  `None` if that can't be told without its value
+    /// (for a filter) or the length of its array (for counting from the end)
+    fn matches(&self, key: Key, child: Option<&JsonValue>, len: Option<usize>) -> Option<bool> {
+        match (self, key) {
+            (Selector::Wildcard, _) => Some(true),
+            (Selector::Name(name), Key::Name(k)) => Some(name == k),
+            (Selector::Index(i), Key::Index(k)) if *i >= 0 => Some(*i == k as i64),
+            (Selector::Index(i), Key::Index(k)) => Some(len? as i64 + i == k as i64),
+            (Selector::Slice(start, end, step), Key::Index(k)) => Self::in_slice(*start, *end, *step, k as i64, len),
+            (Selector::Filter(f), _) => Some(f.test(child?)),
+            _ => Some(false),
+        }
+    }
+
+    /// Whether the index is in the slice, as RFC 9535 has it
+    fn in_slice(start: Option<i64>, end: Option<i64>, step: i64, i: i64, len: Option<usize>) -> Option<bool> {
+        if step == 0 {
+            return Some(false);
+        }
+        if step > 0 && start.unwrap_or(0) >= 0 && end.unwrap_or(0) >= 0 {
+            let lower = start.unwrap_or(0);
+            return Some(i >= lower && end.map_or(true, |e| i < e) && (i - lower) % step == 0);
+        }
+
+        let n = len? as i64;
+        let norm = |x: i64| if x >= 0 { x } else { n + x };
+        if step > 0 {
+            let lower = norm(start.unwrap_or(0)).clamp(0, n);
+            let upper = norm(end.unwrap_or(n)).clamp(0, n);
+            Some(lower <= i && i < upper && (i - lower) % step == 0)
+        } else {
+            let upper = start.map_or(n - 1, norm).clamp(-1, n - 1);
+            let lower = end.map_or(-1, norm).clamp(-1, n - 1);
+            // -i64::MIN would overflow, as an unsigned step it doesn't
+            Some(lower < i && i <= upper && ((upper - i) as u64) % step.unsigned_abs() == 0)
+        }
+    }
+
+    /// Counts from the end of an array, so needs its length
+    fn needs_len(&self) -> bool {
+        match self {
+            Selector::Index(i) => *i < 0,
+            Selector::Slice(start, end, step) => *step < 0 || start.unwrap_or(0) < 0 || end.unwrap_or(0) < 0,
+            _ => false,
+        }
+    }
+}
+
+
+impl Filter {
+
+    fn test(&self, v: &JsonValue) -> bool {
+        match self {
+            Filter::Or(a, b) => a.test(v) || b.test(v),
+            Filter::And(a, b) => a.test(v) && b.test(v),
+            Filter::Not(f) => !f.test(v),
+            Filter::Exists(path) => resolve(path, v).is_some(),
+            Filter::Compare(a, op, b) => {
+                let (a, b) = (a.value(v), b.value(v));
+                match op {
+                    CmpOp::Eq => equal(a, b),
+                    CmpOp::Ne => !equal(a, b),
+                    CmpOp::Lt => less(a, b),
+                    CmpOp::Le => less(a, b) || equal(a, b),
+                    CmpOp::Gt => less(b, a),
+                    CmpOp::Ge => less(b, a) || equal(a, b),
+                }
+            },
+        }
+    }
+}
+
+
+impl Operand {
+    fn value<'v>(&'v self, child: &'v JsonValue) -> Option<&'v JsonValue> {
+        match self {
+            Operand::Path(path) => resolve(path, child),
+            Operand::Value(v) => Some(v),
+        }
+    }
+}
+
+
+/// The value at the path in a filter, if there is one
+fn resolve<'v>(path: &[PathKey], v: &'v JsonValue) -> Option<&'v JsonValue> {
+    path.iter().try_fold(v, |v, key| match key {
+        PathKey::Name(name) => v.get(name),
+        PathKey::Index(i) => {
+            let items = v.as_array()?;
+            let i = if *i < 0 { items.len() as i64 + i } else { *i };
+            if i < 0 { None } else { items.get(i as usize) }
+        },
+    })
+}
+
+
+/// Numbers are equal by value, objects whatever order their members are in, and
+/// nothing (a path to no value) only equals nothing
+fn equal(a: Option<&JsonValue>, b: Option<&JsonValue>) -> bool {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (a, b) => return a.is_none() && b.is_none(),
+    };
+    match (a, b) {
+        (JsonValue::Number(_), JsonValue::Number(_)) => number(a) == number(b),
+        (JsonValue::Array(x), JsonValue::Array(y)) =>
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| equal(Some(x), Some(y))),
+        (JsonValue::Object(x), JsonValue::Object(_)) =>
+            x.len() == b.as_object().map_or(0, |y| y.len()) && x.iter().all(|(name, x)| equal(Some(x), b.get(name))),
+        _ => a == b,
+    }
+}
+
+
+/// Only numbers and strings can be ordered
+fn less(a: Option<&JsonValue>, b: Option<&JsonValue>) -> bool {
+    match (a, b) {
+        (Some(JsonValue::Number(_)), Some(JsonValue::Number(_))) =>
+            matches!((a.and_then(number), b.and_then(number)), (Some(x), Some(y)) if x < y),
+        (Some(JsonValue::String(x)), Some(JsonValue::String(y))) => x < y,
+        _ => false,
+    }
+}
+
+
+fn number(v: &JsonValue) -> Option<f64> {
+    v.as_number()?.as_f64().ok()
+}
+
+
+impl JsonPath {
+
+    /// Parses a path, which starts with `$` for the whole document
+    pub fn parse(path: &str) -> ParseResult<JsonPath> {
+        let mut rc = RewindableChars::new(Cursor::new(path).bytes());
+        let segments = p_path(&mut rc);
+        let end = rc.mark();
+        let more = rc.next().is_some();
+        rc.rewind(end);
+        match segments {
+            Ok(segments) if !more => Ok(JsonPath { segments }),
+            Ok(_) | Err(ParseErr::DidNotMatch) => Err(ParseErr::BadData(format!(
+                "Invalid JSONPath {:?} at column {}", path, RewindableChars::position(&rc).column))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Where we are in the path for a child, given where we are for its parent.
+    /// Each is the index of the next segment to apply, so the child is a match if
+    /// one is past the last segment.  `None` if the child's value or its array's
+    /// length is needed
+    fn step(&self, states: &[usize], key: Key, child: Option<&JsonValue>, len: Option<usize>) -> Option<Vec<usize>> {
+        let mut next = Vec::new();
+        let mut add = |i: usize| if !next.contains(&i) { next.push(i) };
+        for &i in states {
+            let selectors = match self.segments.get(i) {
+                Some(Segment::Child(s)) => s,
+                Some(Segment::Descendant(s)) => {
+                    // carries on looking further down
+                    add(i);
+                    s
+                },
+                None => continue,
+            };
+            for sel in selectors {
+                if sel.matches(key, child, len)? {
+                    add(i + 1);
+                    break;
+                }
+            }
+        }
+        Some(next)
+    }
+
+    /// Whether the children of a value can't be matched one at a time, without its length
+    fn needs_len(&self, states: &[usize]) -> bool {
+        states.iter()
+            .filter_map(|i| self.segments.get(*i))
+            .any(|seg| match seg {
+                Segment::Child(s) | Segment::Descendant(s) => s.iter().any(Selector::needs_len),
+            })
+    }
+
+    /// All the matches in a document that has been built already
+    pub fn find_in<'v>(&self, v: &'v JsonValue) -> Vec<&'v JsonValue> {
+        let mut found = Vec::new();
+        self.collect(v, vec![0], &mut found);
+        found
+    }
+
+    /// Adds the matches in or of `v`, given where we are in the path for it.
+    /// Kept on a stack rather than recursing, as for building values
+    fn collect<'v>(&self, v: &'v JsonValue, states: Vec<usize>, found: &mut Vec<&'v JsonValue>) {
+        let mut todo = vec![(v, states)];
+        while let Some((v, states)) = todo.pop() {
+            if states.contains(&self.segments.len()) {
+                found.push(v);
+            }
+            // children are pushed last first, so they come off in order
+            let start = todo.len();
+            match v {
+                JsonValue::Array(items) => for (i, child) in items.iter().enumerate() {
+                    let next = self.step(&states, Key::Index(i), Some(child), Some(items.len())).unwrap_or_default();
+                    if !next.is_empty() {
+                        todo.push((child, next));
+                    }
+                },
+                JsonValue::Object(members) => for (name, child) in members {
+                    let next = self.step(&states, Key::Name(name), Some(child), None).unwrap_or_default();
+                    if !next.is_empty() {
+                        todo.push((child, next));
+                    }
+                },
+                _ => {},
+            }
+            todo[start..].reverse();
+        }
+    }
+
+    /// The matches in the parser's next value, each as soon as it has been parsed.
+    /// Anything that can't match is skipped without building it
+    pub fn stream<R: JsonInput>(&self, parser: JsonParser<R>) -> JsonPathMatches<'_, R> {
+        JsonPathMatches {
+            path: self,
+            parser,
+            stack: Vec::new(),
+            name: String::new(),
+            found: VecDeque::new(),
+            started: false,
+            done: false,
+        }
+    }
+}
+
+
+/// An array or object the matches are in
+struct Frame {
+    /// Where we are in the path for it
+    states: Vec<usize>,
+    /// Number of the next element, if an array
+    next_index: Option<usize>,
+}
+
+
+/// Matches of a `JsonPath` from the parser's events, see `JsonPath::stream`
+pub struct JsonPathMatches<'p, R: JsonInput> {
+    path: &'p JsonPath,
+    parser: JsonParser<R>,
+    stack: Vec<Frame>,
+
+    /// Name of the member being looked at
+    name: String,
+
+    /// Matches to hand out, there can be several in a value that had to be built
+    found: VecDeque<JsonValue>,
+
+    started: bool,
+    done: bool,
+}
+
+
+impl <'p, R: JsonInput> JsonPathMatches<'p, R> {
+
+    fn next_match(&mut self) -> ParseResult<Option<JsonValue>> {
+        loop {
+            if let Some(v) = self.found.pop_front() {
+                return Ok(Some(v));
+            }
+            if !self.started {
+                self.started = true;
+                self.visit(vec![0])?;
+                continue;
+            }
+            let frame = match self.stack.last_mut() {
+                Some(frame) => frame,
+                None => {
+                    // the document has ended, make sure nothing follows
+                    self.parser.next_token()?;
+                    return Ok(None);
+                },
+            };
+
+            let key = match &mut frame.next_index {
+                Some(i) => {
+                    if self.parser.at_container_end()? {
+                        self.parser.next_token()?;
+                        self.stack.pop();
+                        continue;
+                    }
+                    *i += 1;
+                    Key::Index(*i - 1)
+                },
+                None => match self.parser.next_token()? {
+                    Some(JsonEvent2::PropertyName(name)) => {
+                        self.name.clear();
+                        self.name.push_str(name);
+                        Key::Name(&self.name)
+                    },
+                    _ => {
+                        self.stack.pop();
+                        continue;
+                    },
+                },
+            };
+
+            match self.path.step(&frame.states, key, None, None) {
+                Some(states) => self.visit(states)?,
+                None => {
+                    // a filter needs the child to test
+                    let v = JsonValue::from_parser(&mut self.parser)?;
+                    let states = self.path.step(&frame.states, key, Some(&v), None).unwrap_or_default();
+                    Self::found(self.path, v, states, &mut self.found);
+                },
+            }
+        }
+    }
+
+    /// Deals with the next value, given where we are in the path for it
+    fn visit(&mut self, states: Vec<usize>) -> ParseResult<()> {
+        if states.is_empty() {
+            self.parser.skip_value()?;
+        } else if states.contains(&self.path.segments.len()) || self.path.needs_len(&states) {
+            let v = JsonValue::from_parser(&mut self.parser)?;
+            Self::found(self.path, v, states, &mut self.found);
+        } else {
+            match self.parser.next_token()? {
+                Some(JsonEvent2::ObjectStart) => self.stack.push(Frame { states, next_index: None }),
+                Some(JsonEvent2::ArrayStart) => self.stack.push(Frame { states, next_index: Some(0) }),
+                // nothing inside to match
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues the matches in a value that has been built
+    fn found(path: &JsonPath, v: JsonValue, states: Vec<usize>, queue: &mut VecDeque<JsonValue>) {
+        let mut found = Vec::new();
+        path.collect(&v, states, &mut found);
+        if found.len() == 1 && std::ptr::eq(found[0], &v) {
+            // just the value itself, no need to copy it
+            queue.push_back(v);
+        } else {
+            queue.extend(found.into_iter().cloned());
+        }
+    }
+}
+
+
+/// Each match as a value.  Iterating stops after an error
+impl <'p, R: JsonInput> Iterator for JsonPathMatches<'p, R> {
+    type Item = ParseResult<JsonValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_match();
+        if !matches!(next, Ok(Some(_))) {
+            self.done = true;
+        }
+        next.transpose()
+    }
+}
+
+
+// Parsing the path.  Each parser returns `DidNotMatch` if what's next isn't for it,
+// which is backtracked over by `match_or!`
+
+fn p_path<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Vec<Segment>> {
+    parsers::skip_whitespace(rc)?;
+    parsers::match_char('$', rc)?;
+    let mut segments = Vec::new();
+    loop {
+        let mut segment = None;
+        let matched = match_or!(rc,
+            p_descendant(rc).map(|s| segment = Some(s)),
+            p_dot_child(rc).map(|s| segment = Some(s)),
+            p_bracket(rc).map(|s| segment = Some(Segment::Child(s)))
+        );
+        match (matched, segment) {
+            (Ok(()), Some(s)) => segments.push(s),
+            (Err(ParseErr::DidNotMatch), _) => break,
+            (Err(e), _) => return Err(e),
+            (Ok(()), None) => return Err(ParseErr::DidNotMatch),
+        }
+    }
+    parsers::skip_whitespace(rc)?;
+    Ok(segments)
+}
+
+/// `..name`, `..*` or `..[selectors]`
+fn p_descendant<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Segment> {
+    parsers::match_str("..", rc)?;
+    let mut selectors = None;
+    match_or!(rc,
+        p_bracket(rc).map(|s| selectors = Some(s)),
+        p_shorthand(rc).map(|s| selectors = Some(vec![s]))
+    )?;
+    selectors.map(Segment::Descendant).ok_or(ParseErr::DidNotMatch)
+}
+
+/// `.name` or `.*`
+fn p_dot_child<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Segment> {
+    parsers::match_char('.', rc)?;
+    Ok(Segment::Child(vec![p_shorthand(rc)?]))
+}
+
+fn p_shorthand<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Selector> {
+    let mut selector = None;
+    match_or!(rc,
+        parsers::match_char('*', rc).map(|_| selector = Some(Selector::Wildcard)),
+        p_name(rc).map(|n| selector = Some(Selector::Name(n)))
+    )?;
+    selector.ok_or(ParseErr::DidNotMatch)
+}
+
+fn p_name<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<String> {
+    let mut name = String::new();
+    parsers::capture_while_mand(|c| c == '_' || c.is_alphanumeric(), &mut name, rc)?;
+    Ok(name)
+}
+
+/// `[selector, ...]`
+fn p_bracket<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Vec<Selector>> {
+    parsers::match_char('[', rc)?;
+    parsers::skip_whitespace(rc)?;
+    let mut selectors = vec![p_selector(rc)?];
+    loop {
+        parsers::skip_whitespace(rc)?;
+        if !parsers::match_str_optional(",", rc)? {
+            break;
+        }
+        parsers::skip_whitespace(rc)?;
+        selectors.push(p_selector(rc)?);
+    }
+    parsers::match_char(']', rc)?;
+    Ok(selectors)
+}
+
+fn p_selector<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Selector> {
+    let mut selector = None;
+    match_or!(rc,
+        parsers::match_char('*', rc).map(|_| selector = Some(Selector::Wildcard)),
+        p_quoted(rc).map(|n| selector = Some(Selector::Name(n))),
+        p_filter(rc).map(|f| selector = Some(f)),
+        p_slice(rc).map(|s| selector = Some(s)),
+        p_int(rc).map(|i| selector = Some(Selector::Index(i)))
+    )?;
+    selector.ok_or(ParseErr::DidNotMatch)
+}
+
+fn p_int<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<i64> {
+    let mut s = String::new();
+    if parsers::match_str_optional("-", rc)? {
+        s.push('-');
+    }
+    parsers::capture_while_mand(|c| c.is_ascii_digit(), &mut s, rc)?;
+    s.parse().map_err(|_| ParseErr::BadData(format!("Index {} is out of range", s)))
+}
+
+fn p_optional_int<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Option<i64>> {
+    let m = rc.mark();
+    match p_int(rc) {
+        Ok(i) => Ok(Some(i)),
+        Err(ParseErr::DidNotMatch) => {
+            rc.rewind(m);
+            Ok(None)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// `start:end:step`, any of which can be left out
+fn p_slice<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Selector> {
+    let start = p_optional_int(rc)?;
+    parsers::skip_whitespace(rc)?;
+    parsers::match_char(':', rc)?;
+    parsers::skip_whitespace(rc)?;
+    let end = p_optional_int(rc)?;
+    parsers::skip_whitespace(rc)?;
+    let mut step = None;
+    if parsers::match_str_optional(":", rc)? {
+        parsers::skip_whitespace(rc)?;
+        step = p_optional_int(rc)?;
+    }
+    Ok(Selector::Slice(start, end, step.unwrap_or(1)))
+}
+
+/// String in single or double quotes, with JSON's escapes
+fn p_quoted<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<String> {
+    let quote = match rc.next() {
+        Some(Ok(c)) if c == '\'' || c == '"' => c,
+        Some(Err(e)) => return Err(e),
+        _ => return Err(ParseErr::DidNotMatch),
+    };
+    let mut s = String::new();
+    loop {
+        match rc.next() {
+            Some(Ok(c)) if c == quote => return Ok(s),
+            Some(Ok('\\')) => s.push(p_escape(rc)?),
+            Some(Ok(c)) => s.push(c),
+            Some(Err(e)) => return Err(e),
+            None => return Err(ParseErr::DidNotMatch),
+        }
+    }
+}
+
+fn p_escape<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<char> {
+    let c = match rc.next() {
+        Some(Ok('b')) => '\u{8}',
+        Some(Ok('f')) => '\u{c}',
+        Some(Ok('n')) => '\n',
+        Some(Ok('r')) => '\r',
+        Some(Ok('t')) => '\t',
+        Some(Ok('u')) => {
+            let hex = parsers::capture_n(rc, |c| c.is_ascii_hexdigit(), 4)?;
+            let c = u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32);
+            c.ok_or(ParseErr::DidNotMatch)?
+        },
+        Some(Ok(c)) if c == '\'' || c == '"' || c == '\\' || c == '/' => c,
+        Some(Err(e)) => return Err(e),
+        _ => return Err(ParseErr::DidNotMatch),
+    };
+    Ok(c)
+}
+
+/// `?(expression)` or `?expression`
+fn p_filter<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Selector> {
+    parsers::match_char('?', rc)?;
+    Ok(Selector::Filter(p_or(rc)?))
+}
+
+fn p_or<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Filter> {
+    let mut f = p_and(rc)?;
+    loop {
+        parsers::skip_whitespace(rc)?;
+        if !parsers::match_str_optional("||", rc)? {
+            return Ok(f);
+        }
+        f = Filter::Or(Box::new(f), Box::new(p_and(rc)?));
+    }
+}
+
+fn p_and<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Filter> {
+    let mut f = p_filter_atom(rc)?;
+    loop {
+        parsers::skip_whitespace(rc)?;
+        if !parsers::match_str_optional("&&", rc)? {
+            return Ok(f);
+        }
+        f = Filter::And(Box::new(f), Box::new(p_filter_atom(rc)?));
+    }
+}
+
+/// A comparison, a test a value is there, or one of those in brackets or after `!`
+fn p_filter_atom<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Filter> {
+    parsers::skip_whitespace(rc)?;
+    let mut f = None;
+    match_or!(rc,
+        match_all!(rc,
+            parsers::match_char('(', rc),
+            p_or(rc).map(|x| f = Some(x)),
+            parsers::skip_whitespace(rc),
+            parsers::match_char(')', rc)
+        ),
+        match_all!(rc,
+            parsers::match_char('!', rc),
+            p_filter_atom(rc).map(|x| f = Some(Filter::Not(Box::new(x))))
+        ),
+        p_comparison(rc).map(|x| f = Some(x))
+    )?;
+    f.ok_or(ParseErr::DidNotMatch)
+}
+
+fn p_comparison<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Filter> {
+    let left = p_operand(rc)?;
+    parsers::skip_whitespace(rc)?;
+    let m = rc.mark();
+    match p_cmp_op(rc) {
+        Ok(op) => {
+            parsers::skip_whitespace(rc)?;
+            Ok(Filter::Compare(left, op, p_operand(rc)?))
+        },
+        Err(ParseErr::DidNotMatch) => {
+            rc.rewind(m);
+            match left {
+                Operand::Path(path) => Ok(Filter::Exists(path)),
+                Operand::Value(_) => Err(ParseErr::DidNotMatch),
+            }
+        },
+        Err(e) => Err(e),
+    }
+}
+
+fn p_cmp_op<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<CmpOp> {
+    let mut op = None;
+    match_or!(rc,
+        parsers::match_str("==", rc).map(|_| op = Some(CmpOp::Eq)),
+        parsers::match_str("!=", rc).map(|_| op = Some(CmpOp::Ne)),
+        parsers::match_str("<=", rc).map(|_| op = Some(CmpOp::Le)),
+        parsers::match_str(">=", rc).map(|_| op = Some(CmpOp::Ge)),
+        parsers::match_char('<', rc).map(|_| op = Some(CmpOp::Lt)),
+        parsers::match_char('>', rc).map(|_| op = Some(CmpOp::Gt))
+    )?;
+    op.ok_or(ParseErr::DidNotMatch)
+}
+
+fn p_operand<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Operand> {
+    let mut operand = None;
+    match_or!(rc,
+        p_rel_path(rc).map(|p| operand = Some(Operand::Path(p))),
+        p_literal(rc).map(|v| operand = Some(Operand::Value(v)))
+    )?;
+    operand.ok_or(ParseErr::DidNotMatch)
+}
+
+/// `@` followed by member names and indexes
+fn p_rel_path<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<Vec<PathKey>> {
+    parsers::match_char('@', rc)?;
+    let mut path = Vec::new();
+    loop {
+        let mut key = None;
+        let matched = match_or!(rc,
+            match_all!(rc,
+                parsers::match_char('.', rc),
+                p_name(rc).map(|n| key = Some(PathKey::Name(n)))
+            ),
+            match_all!(rc,
+                parsers::match_char('[', rc),
+                parsers::skip_whitespace(rc),
+                match_or!(rc,
+                    p_quoted(rc).map(|n| key = Some(PathKey::Name(n))),
+                    p_int(rc).map(|i| key = Some(PathKey::Index(i)))
+                ),
+                parsers::skip_whitespace(rc),
+                parsers::match_char(']', rc)
+            )
+        );
+        match (matched, key) {
+            (Ok(()), Some(k)) => path.push(k),
+            (Err(ParseErr::DidNotMatch), _) | (Ok(()), None) => return Ok(path),
+            (Err(e), _) => return Err(e),
+        }
+    }
+}
+
+fn p_literal<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<JsonValue> {
+    let mut v = None;
+    match_or!(rc,
+        p_quoted(rc).map(|s| v = Some(JsonValue::String(s))),
+        p_number(rc).map(|n| v = Some(JsonValue::Number(n))),
+        parsers::match_str("true", rc).map(|_| v = Some(JsonValue::Bool(true))),
+        parsers::match_str("false", rc).map(|_| v = Some(JsonValue::Bool(false))),
+        parsers::match_str("null", rc).map(|_| v = Some(JsonValue::Null))
+    )?;
+    v.ok_or(ParseErr::DidNotMatch)
+}
+
+fn p_number<R: Read>(rc: &mut RewindableChars<R>) -> ParseResult<String> {
+    let mut s = String::new();
+    let number_char = |c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E';
+    parsers::capture_while_mand(number_char, &mut s, rc)?;
+    JsonNumber::parse(&s).map_err(|_| ParseErr::DidNotMatch)?;
+    Ok(s)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::json2::JsonParserBuilder;
+
+    const DOC: &str = r#"{
+        "orders": [
+            {"id": 1, "items": [{"sku": "a", "qty": 1}, {"sku": "b", "qty": 3}]},
+            {"id": 2, "items": [{"sku": "c", "qty": 5, "note": "]}\"["}], "rush": true},
+            {"id": 3, "items": []},
+            {"id": 4, "items": [{"sku": "d", "qty": 2.5e0}, {"sku": "e", "qty": 10, "tags": ["x"]}]}
+        ],
+        "store": {"name": "main", "sku": "s", "tags": ["a", "b", "c", "d", "e"]}
+    }"#;
+
+    /// Matches found by streaming, written out
+    fn stream(path: &str) -> ParseResult<Vec<String>> {
+        let path = JsonPath::parse(path)?;
+        let p = JsonParserBuilder::new().buffer_size(16).build(Cursor::new(DOC));
+        path.stream(p).map(|v| v.map(|v| v.to_string())).collect()
+    }
+
+    /// Matches found in the built document, written out
+    fn find_in(path: &str) -> ParseResult<Vec<String>> {
+        let doc = JsonValue::parse(Cursor::new(DOC))?;
+        Ok(JsonPath::parse(path)?.find_in(&doc).iter().map(|v| v.to_string()).collect())
+    }
+
+    fn check(path: &str, expected: &[&str]) -> ParseResult<()> {
+        assert_eq!(expected, &stream(path)?[..], "streaming {}", path);
+        assert_eq!(expected, &find_in(path)?[..], "built {}", path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse() -> ParseResult<()> {
+        for good in &["$", "$.a", "$['a b'].c[0]", "$..x", "$..*", "$.*[1:3]", "$[::-1]", "$[ 0 , 'a' ]",
+                "$[?(@.x)]", "$[?@.a.b[0] >= -1.5e3]", "$[?(!(@.a == 'x') || @['b'] != null && @[-1])]",
+                " $.a "] {
+            assert!(JsonPath::parse(good).is_ok(), "{}", good);
+        }
+        for bad in &["", "a", "$.", "$[", "$[1", "$['a]", "$[?(@.a >)]", "$[?(@.a]", "$.a b", "$...a", "$[?(1)]"] {
+            assert!(JsonPath::parse(bad).is_err(), "{}", bad);
+        }
+        assert_eq!("Invalid JSONPath \"$.a b\" at column 5", JsonPath::parse("$.a b").unwrap_err().to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_children() -> ParseResult<()> {
+        check("$.store.name", &["\"main\""])?;
+        check("$['store']['tags'][1]", &["\"b\""])?;
+        check("$.orders[*].id", &["1", "2", "3", "4"])?;
+        check("$.orders[1].items[0].note", &["\"]}\\\"[\""])?;
+        check("$.store.*", &["\"main\"", "\"s\"", r#"["a","b","c","d","e"]"#])?;
+        check("$.store['sku', 'name']", &["\"main\"", "\"s\""])?;
+        check("$.nothing", &[])?;
+        check("$.store.name.x", &[])?;
+        check("$", &[&JsonValue::parse(Cursor::new(DOC))?.to_string()])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexes_and_slices() -> ParseResult<()> {
+        check("$.store.tags[-1]", &["\"e\""])?;
+        check("$.store.tags[1:3]", &["\"b\"", "\"c\""])?;
+        check("$.store.tags[::2]", &["\"a\"", "\"c\"", "\"e\""])?;
+        check("$.store.tags[-2:]", &["\"d\"", "\"e\""])?;
+        // in document order, rather than backwards
+        check("$.store.tags[::-2]", &["\"a\"", "\"c\"", "\"e\""])?;
+        check("$.store.tags[3:1:-1]", &["\"c\"", "\"d\""])?;
+        check("$.store.tags[0:0]", &[])?;
+        check("$.store.tags[1:3:0]", &[])?;
+        check("$.store.tags[4, 0, 4]", &["\"a\"", "\"e\""])?;
+        check("$.orders[-1].items[-1].sku", &["\"e\""])?;
+
+        // steps and bounds at the ends of i64
+        check("$.store.tags[::-9223372036854775808]", &["\"e\""])?;
+        check("$.store.tags[::9223372036854775807]", &["\"a\""])?;
+        check("$.store.tags[-9223372036854775808:9223372036854775807]", &["\"a\"", "\"b\"", "\"c\"", "\"d\"", "\"e\""])?;
+        check("$.store.tags[9223372036854775807:-9223372036854775808:-1]", &["\"a\"", "\"b\"", "\"c\"", "\"d\"", "\"e\""])?;
+        check("$.store.tags[-9223372036854775808]", &[])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_descendants() -> ParseResult<()> {
+        check("$..sku", &["\"a\"", "\"b\"", "\"c\"", "\"d\"", "\"e\"", "\"s\""])?;
+        check("$.orders..tags[0]", &["\"x\""])?;
+        check("$..tags.*", &["\"x\"", "\"a\"", "\"b\"", "\"c\"", "\"d\"", "\"e\""])?;
+        check("$..items[1].qty", &["3", "10"])?;
+        check("$..[?(@.rush)].id", &["2"])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_filters() -> ParseResult<()> {
+        check("$.orders[*].items[?(@.qty > 2)].sku", &["\"b\"", "\"c\"", "\"d\"", "\"e\""])?;
+        check("$.orders[*].items[?(@.qty == 2.5)].sku", &["\"d\""])?;
+        check("$.orders[*].items[?(@.qty >= 3 && @.qty <= 5)].sku", &["\"b\"", "\"c\""])?;
+        check("$.orders[*].items[?(@.qty < 2 || @.tags)].sku", &["\"a\"", "\"e\""])?;
+        check("$.orders[?(!@.rush)].id", &["1", "3", "4"])?;
+        check("$.orders[?(@.items[0].sku == 'c')].id", &["2"])?;
+        check("$.orders[?(@.items[-1].sku == \"e\")].id", &["4"])?;
+        check("$.orders[?(@.items[0])].id", &["1", "2", "4"])?;
+        check("$.store.tags[?(@ > 'b')]", &["\"c\"", "\"d\"", "\"e\""])?;
+        check("$.orders[?@.id != 1 && (@.id < 3 || @.id > 3)].id", &["2", "4"])?;
+        check("$.store[?(@ == 'main')]", &["\"main\""])?;
+        check("$.orders[?(@.missing != 1)].id", &["1", "2", "3", "4"])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_errors() -> ParseResult<()> {
+        let path = JsonPath::parse("$..a")?;
+        let results: Vec<ParseResult<JsonValue>> = path.stream(JsonParser::from_slice(br#"[{"a": 1}, {"a": [2"#)).collect();
+        assert_eq!(2, results.len());
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        // trailing data
+        let results: Vec<ParseResult<JsonValue>> = path.stream(JsonParser::from_slice(br#"{"a": 1} 2"#)).collect();
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        Ok(())
+    }
+}
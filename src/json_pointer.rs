@@ -0,0 +1,221 @@
+//! JSON Pointer (RFC 6901), such as `/config/servers/3/host`, to pick out one value.
+//! Over a `JsonParser` only what's needed to find the value is parsed, everything
+//! else is skipped
+
+use std::fmt;
+use crate::internals::{ParseResult, ParseErr};
+use crate::json2::{JsonParser, JsonEvent2, JsonInput};
+use crate::json_value::JsonValue;
+
+
+/// A parsed JSON Pointer, a path of member names and array indexes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPointer {
+    /// Reference tokens with `~1` and `~0` decoded
+    tokens: Vec<String>,
+}
+
+
+impl JsonPointer {
+
+    /// Parses a pointer, either empty for the whole document or a `/` before each
+    /// reference token, where `~1` is a `/` and `~0` a `~`
+    pub fn parse(pointer: &str) -> ParseResult<JsonPointer> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer { tokens: Vec::new() });
+        }
+        if !pointer.starts_with('/') {
+            return Err(Self::invalid(pointer, "it must start with '/'"));
+        }
+        let tokens = pointer[1..].split('/')
+            .map(|t| Self::unescape(t).ok_or_else(|| Self::invalid(pointer, "'~' must be followed by '0' or '1'")))
+            .collect::<ParseResult<Vec<String>>>()?;
+        Ok(JsonPointer { tokens })
+    }
+
+    fn unescape(token: &str) -> Option<String> {
+        let mut s = String::with_capacity(token.len());
+        let mut chars = token.chars();
+        while let Some(c) = chars.next() {
+            if c == '~' {
+                match chars.next() {
+                    Some('0') => s.push('~'),
+                    Some('1') => s.push('/'),
+                    _ => return None,
+                }
+            } else {
+                s.push(c);
+            }
+        }
+        Some(s)
+    }
+
+    fn invalid(pointer: &str, reason: &str) -> ParseErr {
+        ParseErr::BadData(format!("Invalid JSON pointer {:?}, {}", pointer, reason))
+    }
+
+    /// The member names and array indexes, unescaped
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// The index a reference token is, if it can be one.  `-` (after the last
+    /// element) is never found, so isn't one
+    fn array_index(token: &str) -> Option<usize> {
+        let digits = token.bytes().all(|b| b.is_ascii_digit());
+        if !digits || token.is_empty() || (token.starts_with('0') && token.len() > 1) {
+            return None;
+        }
+        token.parse().ok()
+    }
+
+    /// Moves the parser on to the value, skipping everything before it, so its events
+    /// come next.  The next value of the parser is taken to be the document.
+    /// False if there is no such value, in which case how much has been parsed isn't
+    /// defined
+    pub fn seek<R: JsonInput>(&self, p: &mut JsonParser<R>) -> ParseResult<bool> {
+        for token in &self.tokens {
+            match p.next_token()? {
+                Some(JsonEvent2::ObjectStart) => loop {
+                    match p.next_token()? {
+                        Some(JsonEvent2::PropertyName(name)) if name == token => break,
+                        Some(JsonEvent2::PropertyName(_)) => { p.skip_value()?; },
+                        _ => return Ok(false),
+                    }
+                },
+                Some(JsonEvent2::ArrayStart) => {
+                    let index = match Self::array_index(token) {
+                        Some(i) => i,
+                        None => return Ok(false),
+                    };
+                    for _i in 0..index {
+                        if !p.skip_value()? {
+                            return Ok(false);
+                        }
+                    }
+                    if p.at_container_end()? {
+                        return Ok(false);
+                    }
+                },
+                // can't go into anything else
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    /// The value, if there is one, built from the parser without building any more
+    /// of the document
+    pub fn find<R: JsonInput>(&self, p: &mut JsonParser<R>) -> ParseResult<Option<JsonValue>> {
+        match self.seek(p)? {
+            true => JsonValue::from_parser(p).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// The value in a document that has been built already
+    pub fn find_in<'v>(&self, v: &'v JsonValue) -> Option<&'v JsonValue> {
+        self.tokens.iter().try_fold(v, |v, token| match v {
+            JsonValue::Object(_) => v.get(token),
+            JsonValue::Array(_) => v.get_index(Self::array_index(token)?),
+            _ => None,
+        })
+    }
+}
+
+
+/// The pointer as it would be written, with escapes
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::json2::JsonParserBuilder;
+    use std::io::Cursor;
+
+    const DOC: &str = r#"{
+        "config": {
+            "name": "main",
+            "skipped": {"deep": [[1, 2, {"host": "wrong"}], "]}\"{["]},
+            "servers": [
+                {"host": "a", "port": 1},
+                {"host": "b", "port": 2},
+                {"host": "c", "port": 3},
+                {"host": "d", "port": 4, "tags": ["x", "y"]}
+            ]
+        },
+        "a/b": 1, "m~n": 2, "": 3, " ": 4, "01": 5
+    }"#;
+
+    fn find(pointer: &str) -> ParseResult<Option<JsonValue>> {
+        let mut p = JsonParserBuilder::new().buffer_size(7).build(Cursor::new(DOC));
+        JsonPointer::parse(pointer)?.find(&mut p)
+    }
+
+    #[test]
+    fn test_parse() -> ParseResult<()> {
+        let p = JsonPointer::parse("/a~1b/m~0n/~01/3/")?;
+        assert_eq!(vec!["a/b", "m~n", "~1", "3", ""], p.tokens());
+        assert_eq!("/a~1b/m~0n/~01/3/", p.to_string());
+        assert!(JsonPointer::parse("")?.tokens().is_empty());
+
+        for bad in &["a", "/a~", "/~2", "/a/b~x"] {
+            assert!(JsonPointer::parse(bad).is_err(), "{}", bad);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_find() -> ParseResult<()> {
+        assert_eq!(Some("d"), find("/config/servers/3/host")?.as_ref().and_then(|v| v.as_str()));
+        assert_eq!(Some("y"), find("/config/servers/3/tags/1")?.as_ref().and_then(|v| v.as_str()));
+        assert_eq!(Some(r#"{"host":"b","port":2}"#.to_string()), find("/config/servers/1")?.map(|v| v.to_string()));
+        assert_eq!(Some("]}\"{[".to_string()), find("/config/skipped/deep/1")?.and_then(|v| v.as_str().map(String::from)));
+        assert_eq!(Some(JsonValue::Number(String::from("1"))), find("/a~1b")?);
+        assert_eq!(Some(JsonValue::Number(String::from("2"))), find("/m~0n")?);
+        assert_eq!(Some(JsonValue::Number(String::from("3"))), find("/")?);
+        assert_eq!(Some(JsonValue::Number(String::from("4"))), find("/ ")?);
+        assert_eq!(Some(JsonValue::Number(String::from("5"))), find("/01")?);
+        assert!(find("")?.is_some());
+
+        for missing in &["/nope", "/config/servers/4", "/config/servers/-", "/config/servers/01",
+                "/config/servers/x", "/config/name/0", "/config/servers/0/host/x"] {
+            assert_eq!(None, find(missing)?, "{}", missing);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_seek() -> ParseResult<()> {
+        let mut p = JsonParser::from_slice(DOC.as_bytes());
+        assert!(JsonPointer::parse("/config/servers/2")?.seek(&mut p)?);
+
+        // the value's events are next, then the rest of the document
+        assert!(p.next_token()?.unwrap().is_obj_start());
+        assert!(matches!(p.next_token()?, Some(JsonEvent2::PropertyName("host"))));
+        assert!(matches!(p.next_token()?, Some(JsonEvent2::String("c"))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_in() -> ParseResult<()> {
+        let doc = JsonValue::parse(Cursor::new(DOC))?;
+        for pointer in &["", "/config/servers/3/host", "/config/servers/1", "/a~1b", "/", "/01",
+                "/nope", "/config/servers/4", "/config/servers/-", "/config/servers/01", "/config/name/0"] {
+            let ptr = JsonPointer::parse(pointer)?;
+            assert_eq!(find(pointer)?.as_ref(), ptr.find_in(&doc), "{}", pointer);
+        }
+        assert_eq!(Some("a"), doc.pointer("/config/servers/0/host").and_then(|v| v.as_str()));
+        assert_eq!(None, doc.pointer("bad"));
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Push parser, for input that arrives a piece at a time (say from callbacks) rather
+//! than being read.  Each piece is given to the parser with `feed`, then the events
+//! it completes are taken with `next_token`
+
+use crate::internals::{ParseResult, Position};
+use crate::json2::{JsonParser, JsonParserBuilder, JsonEvent2, FeedInput, Fed, PathSegment};
+
+
+/// Parser that is given its input, rather than reading it.  Pieces can end anywhere,
+/// even part way through a string, number or keyword.  A string that isn't all there
+/// yet carries on from where it got to once there is more, other tokens are short
+/// and are scanned again from their start
+pub struct JsonPushParser {
+    parser: JsonParser<FeedInput>,
+}
+
+
+/// What `JsonPushParser::next_token` found next
+#[derive(Debug)]
+pub enum PushEvent<'a> {
+    Event(JsonEvent2<'a>),
+
+    /// The next event isn't all there yet, `feed` some more
+    NeedInput,
+
+    /// The end of the input, after `end_input`
+    End,
+}
+
+
+impl Default for JsonPushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl JsonPushParser {
+
+    pub fn new() -> JsonPushParser {
+        Self::with_builder(JsonParserBuilder::new())
+    }
+
+    /// Parser set up with the options and limits of the builder.  The buffer size isn't
+    /// used, as the input is kept as it is fed
+    pub fn with_builder(builder: JsonParserBuilder) -> JsonPushParser {
+        JsonPushParser {
+            parser: builder.build(FeedInput::default()),
+        }
+    }
+
+    /// Adds the next piece of input.  Only what's not yet parsed is kept, so the
+    /// events of earlier pieces should be taken first
+    pub fn feed(&mut self, bytes: &[u8]) -> ParseResult<()> {
+        self.parser.feed(bytes)
+    }
+
+    /// There is no more input, anything not yet parsed must be complete
+    pub fn end_input(&mut self) {
+        self.parser.end_input();
+    }
+
+    /// The next event, if all of it has been fed.  `End` only comes once `end_input`
+    /// has been called
+    pub fn next_token(&mut self) -> ParseResult<PushEvent<'_>> {
+        Ok(match self.parser.next_fed()? {
+            Fed::Token(t) => PushEvent::Event(self.parser.event(t)),
+            Fed::NeedInput => PushEvent::NeedInput,
+            Fed::End => PushEvent::End,
+        })
+    }
+
+    /// How many arrays and objects we are inside
+    pub fn depth(&self) -> usize {
+        self.parser.depth()
+    }
+
+    /// Path from the top of the document to the value of the last event, see
+    /// `JsonParser::current_path`
+    pub fn current_path(&self) -> Vec<PathSegment<'_>> {
+        self.parser.current_path()
+    }
+
+    /// Position in the input of the start of the last token returned
+    pub fn token_position(&self) -> Position {
+        self.parser.token_position()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::internals::ErrorKind;
+
+    const TEXT: &str = r#"{"name": "push \"parsing\" é 😀 \u00e9\ud83d\ude00", "values": [1, -2.5e10, true, false, null],
+        "nested": {"empty": [], "obj": {}}, "big": 12345678901234567890, "last": 0}"#;
+
+    /// All the events so far, written out so they can be compared with `JsonParser`'s
+    fn take_events(p: &mut JsonPushParser, events: &mut Vec<String>) -> ParseResult<()> {
+        while let PushEvent::Event(e) = p.next_token()? {
+            events.push(format!("{:?}", e));
+        }
+        Ok(())
+    }
+
+    fn pushed_events(pieces: &[&[u8]]) -> ParseResult<Vec<String>> {
+        let mut p = JsonPushParser::new();
+        let mut events = Vec::new();
+        for piece in pieces {
+            p.feed(piece)?;
+            take_events(&mut p, &mut events)?;
+        }
+        p.end_input();
+        take_events(&mut p, &mut events)?;
+        Ok(events)
+    }
+
+    fn sync_events(text: &str) -> ParseResult<Vec<String>> {
+        let mut p = JsonParser::from_slice(text.as_bytes());
+        let mut events = Vec::new();
+        while let Some(e) = p.next_token()? {
+            events.push(format!("{:?}", e));
+        }
+        Ok(events)
+    }
+
+    #[test]
+    fn test_split_anywhere() -> ParseResult<()> {
+        let expected = sync_events(TEXT)?;
+        let bytes = TEXT.as_bytes();
+
+        // a byte at a time
+        let pieces: Vec<&[u8]> = bytes.chunks(1).collect();
+        assert_eq!(expected, pushed_events(&pieces)?);
+
+        // in two, at every place
+        for i in 0..=bytes.len() {
+            assert_eq!(expected, pushed_events(&[&bytes[..i], &bytes[i..]])?, "split at {}", i);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_as_fed() -> ParseResult<()> {
+        let mut p = JsonPushParser::new();
+        p.feed(b"[ tr")?;
+        assert!(matches!(p.next_token()?, PushEvent::Event(JsonEvent2::ArrayStart)));
+        assert!(matches!(p.next_token()?, PushEvent::NeedInput));
+        // still that of the last event
+        assert_eq!(1, p.token_position().column);
+        p.feed(b"ue, \"ab")?;
+        assert!(matches!(p.next_token()?, PushEvent::Event(JsonEvent2::Boolean(true))));
+        assert!(matches!(p.next_token()?, PushEvent::NeedInput));
+        p.feed(b"c\", 12")?;
+        assert!(matches!(p.next_token()?, PushEvent::Event(JsonEvent2::String("abc"))));
+        // the number could go on
+        assert!(matches!(p.next_token()?, PushEvent::NeedInput));
+        assert_eq!(1, p.depth());
+        p.feed(b"3]")?;
+        match p.next_token()? {
+            PushEvent::Event(JsonEvent2::Number(n)) => assert_eq!("123", n.as_str()),
+            e => panic!("{:?}", e),
+        }
+        assert!(matches!(p.next_token()?, PushEvent::Event(JsonEvent2::ArrayEnd)));
+        assert!(matches!(p.next_token()?, PushEvent::NeedInput));
+        p.end_input();
+        assert!(matches!(p.next_token()?, PushEvent::End));
+        assert!(matches!(p.next_token()?, PushEvent::End));
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_string() -> ParseResult<()> {
+        // carried on from where it got to, so a byte at a time doesn't scan it again each time
+        let text: String = (0..100_000).map(|i| match i % 50 {
+            0 => "\\u00e9",
+            10 => "é",
+            20 => "\\n",
+            30 => "😀",
+            _ => "x",
+        }).collect();
+        let json = format!("[\n \"{}\",\n \"next\"]", text);
+        let mut p = JsonPushParser::new();
+        let mut strings = Vec::new();
+        for piece in json.as_bytes().chunks(1) {
+            p.feed(piece)?;
+            while let PushEvent::Event(e) = p.next_token()? {
+                if let JsonEvent2::String(s) = e {
+                    strings.push(String::from(s));
+                    let at = p.token_position();
+                    assert_eq!((strings.len() + 1, 2), (at.line, at.column));
+                }
+            }
+        }
+        p.end_input();
+        assert!(matches!(p.next_token()?, PushEvent::End));
+        let expected = sync_events(&json)?;
+        assert_eq!(expected[1], format!("String({:?})", strings[0]));
+        assert_eq!(vec!["next"], &strings[1..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_in_split_strings() {
+        // errors point where they would if it was all fed at once
+        let cases: &[&[&[u8]]] = &[
+            &[b"[\n \"ab", b"cd", b"\\u1", b"2x4\"]"],
+            &[b"[\n \"ab", b"\\", b"q\"]"],
+            &[b"[\n \"ab\xE2", b"\x82", b"(\"]"],
+            &[b"[\n \"ab", b"\xF0", b"\x9F\n\"]"],
+            &[b"[\n \"ab", b"cd\x01\"]"],
+        ];
+        for pieces in cases {
+            let mut p = JsonPushParser::new();
+            let mut events = Vec::new();
+            let e = pieces.iter()
+                .find_map(|piece| p.feed(piece).and_then(|_| take_events(&mut p, &mut events)).err())
+                .unwrap();
+
+            let all = pieces.concat();
+            let mut sync = JsonParser::from_slice(&all);
+            let expected = loop {
+                if let Err(e) = sync.next_token() {
+                    break e;
+                }
+            };
+            assert_eq!(expected.to_string(), e.to_string());
+            assert_eq!(2, e.position().unwrap().line);
+        }
+    }
+
+    #[test]
+    fn test_current_path() -> ParseResult<()> {
+        // names split between pieces, and a piece ending while a name is the path
+        let mut p = JsonPushParser::new();
+        let mut paths = Vec::new();
+        for piece in &["{\"na", "me\": {\"in", "ner\": [1", ", {\"x\"", ": 2}], \"next", "\": 3}, \"b\\", "u00e9\": 4}"] {
+            p.feed(piece.as_bytes())?;
+            while let PushEvent::Event(e) = p.next_token()? {
+                let e = match e.as_number() {
+                    Some(n) => String::from(n.as_str()),
+                    None => format!("{:?}", e),
+                };
+                let path: String = p.current_path().iter().map(|s| s.to_string()).collect();
+                paths.push(format!("{} ${}", e, path));
+            }
+        }
+        p.end_input();
+        assert!(matches!(p.next_token()?, PushEvent::End));
+        assert_eq!(vec![
+            "ObjectStart $",
+            "PropertyName(\"name\") $.name", "ObjectStart $.name",
+            "PropertyName(\"inner\") $.name.inner", "ArrayStart $.name.inner",
+            "1 $.name.inner[0]", "ObjectStart $.name.inner[1]",
+            "PropertyName(\"x\") $.name.inner[1].x", "2 $.name.inner[1].x",
+            "ObjectEnd $.name.inner[1]", "ArrayEnd $.name.inner",
+            "PropertyName(\"next\") $.name.next", "3 $.name.next", "ObjectEnd $.name",
+            "PropertyName(\"bé\") $.bé", "4 $.bé", "ObjectEnd $",
+        ], paths);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        // a bad byte is an error straight away, wherever the pieces end
+        let mut p = JsonPushParser::new();
+        let mut events = Vec::new();
+        p.feed(b"[1,\n 2 ").unwrap();
+        take_events(&mut p, &mut events).unwrap();
+        p.feed(b"3]").unwrap();
+        let e = take_events(&mut p, &mut events).unwrap_err();
+        assert_eq!("Unexpected '3', expected ',' or ']' at line 2, column 4 in $[1]", e.to_string());
+
+        // ending part way through
+        for text in &["", "[1, 2", "{\"a\": \"b", "12.", "tru"] {
+            let e = pushed_events(&[text.as_bytes()]).unwrap_err();
+            assert!(matches!(e.kind(), Some(ErrorKind::UnexpectedEof) | Some(ErrorKind::InvalidNumber(_))), "{}: {}", text, e);
+        }
+
+        let mut p = JsonPushParser::with_builder(JsonParserBuilder::new().max_document_size(10));
+        p.feed(b"[1, 2, ").unwrap();
+        take_events(&mut p, &mut events).unwrap();
+        let e = p.feed(b"3, 4]").unwrap_err();
+        assert!(matches!(e.kind(), Some(ErrorKind::LimitExceeded { .. })));
+        // a smaller piece doesn't get round it
+        assert!(p.feed(b"3").is_err());
+
+        p.end_input();
+        assert!(p.feed(b"[]").is_err());
+    }
+}
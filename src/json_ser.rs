@@ -0,0 +1,598 @@
+//! serde `Serializer` that writes JSON through a `JsonWriter`, compact or pretty printed.
+//! Enums are externally tagged, as the `Deserializer` reads them
+
+use std::fmt;
+use std::io::Write;
+use serde::ser::{self, Serialize};
+use crate::internals::{ParseResult, ParseErr};
+use crate::json_number::JsonNumber;
+use crate::json_writer::JsonWriter;
+
+
+/// Writes the value as compact JSON
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(w: W, value: &T) -> ParseResult<()> {
+    let mut ser = Serializer::new(w);
+    value.serialize(&mut ser)?;
+    ser.into_inner().map(|_| ())
+}
+
+/// Writes the value as pretty printed JSON
+pub fn to_writer_pretty<W: Write, T: Serialize + ?Sized>(w: W, value: &T) -> ParseResult<()> {
+    let mut ser = Serializer::pretty(w);
+    value.serialize(&mut ser)?;
+    ser.into_inner().map(|_| ())
+}
+
+/// The value as compact JSON
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> ParseResult<String> {
+    let mut out = Vec::with_capacity(128);
+    to_writer(&mut out, value)?;
+    // only ever valid UTF-8 is written
+    Ok(unsafe { String::from_utf8_unchecked(out) })
+}
+
+/// The value as pretty printed JSON
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> ParseResult<String> {
+    let mut out = Vec::with_capacity(128);
+    to_writer_pretty(&mut out, value)?;
+    // only ever valid UTF-8 is written
+    Ok(unsafe { String::from_utf8_unchecked(out) })
+}
+
+
+impl ser::Error for ParseErr {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseErr::BadData(msg.to_string())
+    }
+}
+
+
+/// Serializer writing to `W`.  Each value serialized is a whole document
+pub struct Serializer<W: Write> {
+    writer: JsonWriter<W>,
+}
+
+
+impl <W: Write> Serializer<W> {
+
+    /// Serializer for compact JSON, without any whitespace
+    pub fn new(w: W) -> Serializer<W> {
+        Serializer { writer: JsonWriter::new(w) }
+    }
+
+    /// Serializer for pretty printed JSON, with each element and member on its own line
+    pub fn pretty(w: W) -> Serializer<W> {
+        Serializer { writer: JsonWriter::pretty(w) }
+    }
+
+    /// Checks a whole value has been written, and hands back the output
+    pub fn into_inner(self) -> ParseResult<W> {
+        self.writer.finish()
+    }
+
+    /// Starts an object with the variant's name as its one member, the variant's
+    /// content is the member's value
+    fn start_variant(&mut self, variant: &str) -> ParseResult<()> {
+        self.writer.start_object()?;
+        self.writer.property_name(variant)
+    }
+}
+
+
+impl <'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = ParseErr;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> ParseResult<()> {
+        self.writer.boolean(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> ParseResult<()> {
+        self.writer.integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> ParseResult<()> {
+        // may be too big for an i128
+        self.writer.number(&JsonNumber::parse(&v.to_string())?)
+    }
+
+    /// Written as the shortest text that reads back as the same f32, rather than
+    /// with all the digits of the f64 it widens to
+    fn serialize_f32(self, v: f32) -> ParseResult<()> {
+        match v.is_finite() {
+            true => self.writer.number(&JsonNumber::parse(&format!("{:?}", v))?),
+            false => self.writer.float(v as f64),
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> ParseResult<()> {
+        self.writer.float(v)
+    }
+
+    fn serialize_char(self, v: char) -> ParseResult<()> {
+        self.writer.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> ParseResult<()> {
+        self.writer.string(v)
+    }
+
+    /// An array of numbers, as JSON has no bytes
+    fn serialize_bytes(self, v: &[u8]) -> ParseResult<()> {
+        self.writer.start_array()?;
+        for b in v {
+            self.writer.integer(*b)?;
+        }
+        self.writer.end_array()
+    }
+
+    fn serialize_none(self) -> ParseResult<()> {
+        self.writer.null()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> ParseResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ParseResult<()> {
+        self.writer.null()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> ParseResult<()> {
+        self.writer.null()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> ParseResult<()> {
+        self.writer.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> ParseResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+            variant: &'static str, value: &T) -> ParseResult<()> {
+        self.start_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.writer.end_object()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> ParseResult<Compound<'a, W>> {
+        self.writer.start_array()?;
+        Ok(Compound { ser: self, variant: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> ParseResult<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> ParseResult<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str,
+            _len: usize) -> ParseResult<Compound<'a, W>> {
+        self.start_variant(variant)?;
+        self.writer.start_array()?;
+        Ok(Compound { ser: self, variant: true })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> ParseResult<Compound<'a, W>> {
+        self.writer.start_object()?;
+        Ok(Compound { ser: self, variant: false })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> ParseResult<Compound<'a, W>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str,
+            _len: usize) -> ParseResult<Compound<'a, W>> {
+        self.start_variant(variant)?;
+        self.writer.start_object()?;
+        Ok(Compound { ser: self, variant: true })
+    }
+}
+
+
+/// The elements of an array or members of an object being written.  The writer
+/// keeps track of which, so they all share this
+pub struct Compound<'a, W: Write> {
+    ser: &'a mut Serializer<W>,
+    /// Inside the object naming an enum variant, which is closed too at the end
+    variant: bool,
+}
+
+impl <W: Write> Compound<'_, W> {
+    fn end_array(self) -> ParseResult<()> {
+        self.ser.writer.end_array()?;
+        self.end_variant()
+    }
+
+    fn end_object(self) -> ParseResult<()> {
+        self.ser.writer.end_object()?;
+        self.end_variant()
+    }
+
+    fn end_variant(self) -> ParseResult<()> {
+        match self.variant {
+            true => self.ser.writer.end_object(),
+            false => Ok(()),
+        }
+    }
+}
+
+impl <W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_array()
+    }
+}
+
+impl <W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_array()
+    }
+}
+
+impl <W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_array()
+    }
+}
+
+impl <W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_array()
+    }
+}
+
+impl <W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> ParseResult<()> {
+        key.serialize(MapKey { ser: &mut *self.ser })
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> ParseResult<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_object()
+    }
+}
+
+impl <W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ParseResult<()> {
+        self.ser.writer.property_name(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_object()
+    }
+}
+
+impl <W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> ParseResult<()> {
+        self.ser.writer.property_name(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> ParseResult<()> {
+        self.end_object()
+    }
+}
+
+
+/// Writes a map key as a member name.  Strings, chars, unit variants and numbers
+/// can be keys, numbers are written as their text
+struct MapKey<'a, W: Write> {
+    ser: &'a mut Serializer<W>,
+}
+
+impl <W: Write> MapKey<'_, W> {
+    fn name(self, name: &str) -> ParseResult<()> {
+        self.ser.writer.property_name(name)
+    }
+
+    fn not_a_key(what: &str) -> ParseErr {
+        ParseErr::BadData(format!("A {} can't be a map key, only strings and numbers can", what))
+    }
+}
+
+/// Numbers as keys, written as their text
+macro_rules! serialize_key_number {
+    ($( $method:ident $t:ty ),*) => {
+        $(
+            fn $method(self, v: $t) -> ParseResult<()> {
+                self.name(&v.to_string())
+            }
+        )*
+    }
+}
+
+impl <W: Write> ser::Serializer for MapKey<'_, W> {
+    type Ok = ();
+    type Error = ParseErr;
+    type SerializeSeq = ser::Impossible<(), ParseErr>;
+    type SerializeTuple = ser::Impossible<(), ParseErr>;
+    type SerializeTupleStruct = ser::Impossible<(), ParseErr>;
+    type SerializeTupleVariant = ser::Impossible<(), ParseErr>;
+    type SerializeMap = ser::Impossible<(), ParseErr>;
+    type SerializeStruct = ser::Impossible<(), ParseErr>;
+    type SerializeStructVariant = ser::Impossible<(), ParseErr>;
+
+    serialize_key_number! {
+        serialize_i8 i8, serialize_i16 i16, serialize_i32 i32, serialize_i64 i64, serialize_i128 i128,
+        serialize_u8 u8, serialize_u16 u16, serialize_u32 u32, serialize_u64 u64, serialize_u128 u128
+    }
+
+    fn serialize_str(self, v: &str) -> ParseResult<()> {
+        self.name(v)
+    }
+
+    fn serialize_char(self, v: char) -> ParseResult<()> {
+        self.name(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> ParseResult<()> {
+        self.name(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> ParseResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> ParseResult<()> {
+        Err(Self::not_a_key("boolean"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> ParseResult<()> {
+        Err(Self::not_a_key("float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> ParseResult<()> {
+        Err(Self::not_a_key("float"))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> ParseResult<()> {
+        Err(Self::not_a_key("byte array"))
+    }
+
+    fn serialize_none(self) -> ParseResult<()> {
+        Err(Self::not_a_key("null"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> ParseResult<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> ParseResult<()> {
+        Err(Self::not_a_key("null"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> ParseResult<()> {
+        Err(Self::not_a_key("null"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+            _variant: &'static str, _value: &T) -> ParseResult<()> {
+        Err(Self::not_a_key("enum variant with content"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> ParseResult<Self::SerializeSeq> {
+        Err(Self::not_a_key("sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> ParseResult<Self::SerializeTuple> {
+        Err(Self::not_a_key("tuple"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> ParseResult<Self::SerializeTupleStruct> {
+        Err(Self::not_a_key("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str,
+            _len: usize) -> ParseResult<Self::SerializeTupleVariant> {
+        Err(Self::not_a_key("enum variant with content"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> ParseResult<Self::SerializeMap> {
+        Err(Self::not_a_key("map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> ParseResult<Self::SerializeStruct> {
+        Err(Self::not_a_key("struct"))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str,
+            _len: usize) -> ParseResult<Self::SerializeStructVariant> {
+        Err(Self::not_a_key("enum variant with content"))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::json_de::from_str;
+    use serde::{Serialize, Deserialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect(u32, u32),
+        Poly { sides: u8, name: Option<String> },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Unit;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Id(u64);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        id: Id,
+        name: String,
+        shapes: Vec<Shape>,
+        counts: BTreeMap<u32, i128>,
+        by_shape: BTreeMap<String, (f32, bool)>,
+        empty: Vec<u8>,
+        nothing: Option<Unit>,
+        letter: char,
+        big: u128,
+    }
+
+    fn doc() -> Doc {
+        let mut counts = BTreeMap::new();
+        counts.insert(7, i128::MIN);
+        counts.insert(12, 0);
+        let mut by_shape = BTreeMap::new();
+        by_shape.insert(String::from("tab\t\"quote\""), (0.1, true));
+        Doc {
+            id: Id(u64::MAX),
+            name: String::from("é \u{1} \\"),
+            shapes: vec![Shape::Point, Shape::Circle(-1.5e-7), Shape::Rect(2, 3), Shape::Poly { sides: 6, name: None }],
+            counts,
+            by_shape,
+            empty: Vec::new(),
+            nothing: None,
+            letter: '\n',
+            big: u128::MAX,
+        }
+    }
+
+    #[test]
+    fn test_compact() -> ParseResult<()> {
+        assert_eq!(concat!(r#"{"id":18446744073709551615,"name":"é \u0001 \\","#,
+            r#""shapes":["Point",{"Circle":-1.5e-7},{"Rect":[2,3]},{"Poly":{"sides":6,"name":null}}],"#,
+            r#""counts":{"7":-170141183460469231731687303715884105728,"12":0},"#,
+            r#""by_shape":{"tab\t\"quote\"":[0.1,true]},"empty":[],"nothing":null,"letter":"\n","#,
+            r#""big":340282366920938463463374607431768211455}"#), to_string(&doc())?);
+        assert_eq!(r#""just a string""#, to_string("just a string")?);
+        assert_eq!("null", to_string(&Unit)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pretty() -> ParseResult<()> {
+        let shapes = vec![Shape::Point, Shape::Rect(2, 3), Shape::Poly { sides: 6, name: Some(String::from("hex")) }];
+        let expected = r#"[
+  "Point",
+  {
+    "Rect": [
+      2,
+      3
+    ]
+  },
+  {
+    "Poly": {
+      "sides": 6,
+      "name": "hex"
+    }
+  }
+]"#;
+        assert_eq!(expected, to_string_pretty(&shapes)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> ParseResult<()> {
+        assert_eq!(doc(), from_str::<Doc>(&to_string(&doc())?)?);
+        assert_eq!(doc(), from_str::<Doc>(&to_string_pretty(&doc())?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(to_string(&f64::NAN).is_err());
+        assert!(to_string(&f32::INFINITY).is_err());
+
+        let mut bad_keys = BTreeMap::new();
+        bad_keys.insert(vec![1], 1);
+        let e = to_string(&bad_keys).unwrap_err();
+        assert_eq!("A sequence can't be a map key, only strings and numbers can", e.to_string());
+
+        // nothing written if there's no value
+        assert!(Serializer::new(Vec::new()).into_inner().is_err());
+    }
+}
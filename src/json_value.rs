@@ -0,0 +1,322 @@
+//! Owned JSON documents, built from the events of a `JsonParser`
+
+use std::fmt;
+use std::io::Read;
+use std::ops::Index;
+use crate::internals::{ParseResult, ParseErr, ErrorKind};
+use crate::json2::{JsonParser, JsonParserBuilder, JsonEvent2, JsonInput};
+use crate::json_number::JsonNumber;
+use crate::json_pointer::JsonPointer;
+use crate::json_writer::write_escaped;
+
+
+/// A JSON value, owning all its data.  Cloning, comparing, formatting and dropping it
+/// recurse, once for each level of nesting
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+
+    /// The number as it appeared in the input, see `as_number` to convert it
+    Number(String),
+
+    String(String),
+    Array(Vec<JsonValue>),
+
+    /// Members in the order they appeared in the input
+    Object(Vec<(String, JsonValue)>),
+}
+
+
+/// What indexing returns when there is no such member or element
+static NULL: JsonValue = JsonValue::Null;
+
+/// How deeply `parse` lets arrays and objects nest, deep enough for any sensible
+/// document while leaving plenty of call stack to clone, format and drop it
+const MAX_DEPTH: usize = 128;
+
+
+impl JsonValue {
+
+    /// Parses a whole value from the reader, which must not have anything after it.
+    /// Arrays and objects can nest 128 deep, use `from_parser` to set other limits
+    pub fn parse<R: Read>(r: R) -> ParseResult<JsonValue> {
+        let mut p = JsonParserBuilder::new().max_depth(MAX_DEPTH).build(r);
+        let v = Self::from_parser(&mut p)?;
+        // errors if there is more
+        p.next_token()?;
+        Ok(v)
+    }
+
+    /// Builds the next value from the parser's events.  If the parser has just
+    /// returned a property name, this is the member's value.
+    /// Nested arrays and objects are kept on a stack, rather than recursing, but
+    /// dropping the value recurses, so the parser should limit how deep they can be
+    pub fn from_parser<R: JsonInput>(p: &mut JsonParser<R>) -> ParseResult<JsonValue> {
+        // arrays and objects being built, with the name they go under in their parent
+        let mut stack: Vec<(Option<String>, JsonValue)> = Vec::new();
+        let mut name: Option<String> = None;
+
+        loop {
+            let value = match p.next_token()? {
+                Some(JsonEvent2::PropertyName(n)) => {
+                    name = Some(String::from(n));
+                    continue;
+                },
+                Some(JsonEvent2::ObjectStart) => {
+                    stack.push((name.take(), JsonValue::Object(Vec::new())));
+                    continue;
+                },
+                Some(JsonEvent2::ArrayStart) => {
+                    stack.push((name.take(), JsonValue::Array(Vec::new())));
+                    continue;
+                },
+                Some(JsonEvent2::ObjectEnd) | Some(JsonEvent2::ArrayEnd) => match stack.pop() {
+                    Some((n, v)) => {
+                        name = n;
+                        v
+                    },
+                    None => return Err(ParseErr::BadData(String::from("No value, the enclosing array or object has ended"))),
+                },
+                Some(JsonEvent2::String(s)) => JsonValue::String(String::from(s)),
+                Some(JsonEvent2::Number(n)) => JsonValue::Number(String::from(n.as_str())),
+                Some(JsonEvent2::Boolean(b)) => JsonValue::Bool(b),
+                Some(JsonEvent2::Null) => JsonValue::Null,
+                None => return Err(ErrorKind::UnexpectedEof.into()),
+            };
+
+            // add to the parent, or we're done
+            match stack.last_mut() {
+                Some((_, JsonValue::Array(items))) => items.push(value),
+                Some((_, JsonValue::Object(members))) => members.push((name.take().unwrap_or_default(), value)),
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The number, if this is a valid JSON number
+    pub fn as_number(&self) -> Option<JsonNumber<'_>> {
+        match self {
+            JsonValue::Number(n) => JsonNumber::parse(n).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// The first member with the name, if this is an object
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        self.as_object()?.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// The element at the index, if this is an array
+    pub fn get_index(&self, index: usize) -> Option<&JsonValue> {
+        self.as_array()?.get(index)
+    }
+
+    /// The value a JSON pointer (such as `/a/0/b`) refers to, if the pointer is valid
+    /// and there is one, see `JsonPointer`
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        JsonPointer::parse(pointer).ok()?.find_in(self)
+    }
+}
+
+
+/// Indexing by name gives `Null` if there is no such member
+impl Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, name: &str) -> &JsonValue {
+        self.get(name).unwrap_or(&NULL)
+    }
+}
+
+/// Indexing by position gives `Null` if there is no such element
+impl Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &JsonValue {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+
+/// Compact JSON, without any whitespace
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => f.write_str("null"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) => f.write_str(n),
+            JsonValue::String(s) => write_escaped(s, |s| f.write_str(s)),
+            JsonValue::Array(items) => {
+                f.write_str("[")?;
+                for (i, v) in items.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                f.write_str("]")
+            },
+            JsonValue::Object(members) => {
+                f.write_str("{")?;
+                for (i, (n, v)) in members.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write_escaped(n, |s| f.write_str(s))?;
+                    write!(f, ":{}", v)?;
+                }
+                f.write_str("}")
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Cursor;
+
+    fn parse(s: &str) -> ParseResult<JsonValue> {
+        JsonValue::parse(Cursor::new(s.as_bytes()))
+    }
+
+    #[test]
+    fn test_parse_values() -> ParseResult<()> {
+        assert_eq!(JsonValue::Null, parse("null")?);
+        assert_eq!(JsonValue::Bool(true), parse(" true ")?);
+        assert_eq!(JsonValue::Number(String::from("-1.5e3")), parse("-1.5e3")?);
+        assert_eq!(JsonValue::String(String::from("a\"b")), parse(r#""a\"b""#)?);
+        assert_eq!(JsonValue::Array(vec![]), parse("[]")?);
+        assert_eq!(JsonValue::Object(vec![]), parse("{}")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_document() -> ParseResult<()> {
+        let v = parse(r#"{"name": "parsely", "tags": ["json", "fast"], "size": {"kb": 12, "exact": false}, "none": null}"#)?;
+
+        assert_eq!(Some("parsely"), v["name"].as_str());
+        assert_eq!(Some("fast"), v["tags"][1].as_str());
+        assert_eq!(12, v["size"]["kb"].as_number().unwrap().as_i64()?);
+        assert_eq!(Some(false), v["size"]["exact"].as_bool());
+        assert!(v["none"].is_null());
+
+        // missing members and elements are null, but can be told apart with get
+        assert!(v["missing"].is_null());
+        assert!(v["tags"][5].is_null());
+        assert!(v["name"][0].is_null());
+        assert!(v.get("missing").is_none());
+        assert!(v.get("none").is_some());
+
+        // members keep their order
+        let names: Vec<&str> = v.as_object().unwrap().iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(vec!["name", "tags", "size", "none"], names);
+        Ok(())
+    }
+
+    #[test]
+    fn test_display() -> ParseResult<()> {
+        let text = r#"{"a":[1,2.5,-3e10,[],{}],"b\n":{"c":null,"d":true},"e":"x\"\\\u0001yé"}"#;
+        let v = parse(text)?;
+        assert_eq!(text.replace("\\u00e9", "\u{e9}"), v.to_string());
+
+        // what's written parses back the same
+        assert_eq!(v, parse(&v.to_string())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_deep_nesting() -> ParseResult<()> {
+        let depth = 10000;
+        let text = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let mut p = JsonParser::new(Cursor::new(text.as_bytes()));
+        let mut v = JsonValue::from_parser(&mut p)?;
+        let mut inner = &v;
+        for _i in 1..depth {
+            inner = &inner[0];
+        }
+        assert_eq!(Some(0), inner.as_array().map(|a| a.len()));
+
+        // dropping a tree this deep would use the call stack, so take it apart
+        while let JsonValue::Array(mut items) = v {
+            v = items.pop().unwrap_or(JsonValue::Null);
+        }
+
+        // parse has a limit, so there is stack to spare for formatting
+        let text = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert_eq!(text, parse(&text)?.to_string());
+        let text = format!("[{}]", text);
+        assert_eq!(Some(&ErrorKind::DepthExceeded), parse(&text).unwrap_err().kind());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let text = format!("{}{}", "[".repeat(100), "]".repeat(100));
+        let mut p = JsonParserBuilder::new().max_depth(100).build(Cursor::new(text.as_bytes()));
+        assert!(JsonValue::from_parser(&mut p).is_ok());
+        let mut p = JsonParserBuilder::new().max_depth(99).build(Cursor::new(text.as_bytes()));
+        let e = JsonValue::from_parser(&mut p).unwrap_err();
+        assert_eq!(Some(&ErrorKind::DepthExceeded), e.kind());
+    }
+
+    #[test]
+    fn test_from_parser() -> ParseResult<()> {
+        // pick out just the value of one member
+        let mut p = JsonParser::new(Cursor::new(r#"{"skip": 1, "want": {"x": [true]}, "after": 2}"#.as_bytes()));
+        loop {
+            if let Some(JsonEvent2::PropertyName("want")) = p.next_token()? {
+                break;
+            }
+        }
+        let v = JsonValue::from_parser(&mut p)?;
+        assert_eq!(r#"{"x":[true]}"#, v.to_string());
+        assert!(matches!(p.next_token()?, Some(JsonEvent2::PropertyName("after"))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for x in &["", "[1,", r#"{"a":}"#, "[1 2]", "[1] 2"] {
+            assert!(parse(x).is_err(), "{} should not parse", x);
+        }
+        assert_eq!(Some(&ErrorKind::UnexpectedEof), parse("").unwrap_err().kind());
+    }
+}
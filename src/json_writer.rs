@@ -0,0 +1,376 @@
+//! Writes JSON, from the same events the parser produces.  Lots of small writes are
+//! made, so wrap files or sockets in a `BufWriter`
+
+use std::io::Write;
+use crate::internals::{ParseResult, ParseErr};
+use crate::json2::JsonEvent2;
+use crate::json_number::JsonNumber;
+
+
+/// Keeps track of where we are in the output, as `JsonStackItem` does for the parser
+#[derive(Debug)]
+enum WriterStackItem {
+    /// Where value is the number of elements written so far
+    Array(usize),
+    /// Where value is the number of members written so far
+    Object(usize),
+}
+
+
+pub struct JsonWriter<W: Write> {
+    write: W,
+
+    stack: Vec<WriterStackItem>,
+
+    /// Set once a member name has been written, the member's value is next
+    after_name: bool,
+
+    /// The top level value has been written
+    done: bool,
+
+    /// Indent for each level when pretty printing, none for compact output
+    indent: Option<&'static str>,
+}
+
+
+impl <W: Write> JsonWriter<W> {
+
+    /// Writer for compact JSON, without any whitespace
+    pub fn new(w: W) -> JsonWriter<W> {
+        JsonWriter {
+            write: w,
+            stack: Vec::with_capacity(10),
+            after_name: false,
+            done: false,
+            indent: None,
+        }
+    }
+
+    /// Writer for pretty printed JSON, with each element and member on its own line
+    pub fn pretty(w: W) -> JsonWriter<W> {
+        JsonWriter { indent: Some("  "), ..Self::new(w) }
+    }
+
+    /// Writes the event, so events from a `JsonParser` can be copied across
+    pub fn write_event(&mut self, e: &JsonEvent2<'_>) -> ParseResult<()> {
+        match e {
+            JsonEvent2::String(s) => self.string(s),
+            JsonEvent2::PropertyName(n) => self.property_name(n),
+            JsonEvent2::Number(n) => self.number(n),
+            JsonEvent2::Boolean(b) => self.boolean(*b),
+            JsonEvent2::Null => self.null(),
+            JsonEvent2::ObjectStart => self.start_object(),
+            JsonEvent2::ObjectEnd => self.end_object(),
+            JsonEvent2::ArrayStart => self.start_array(),
+            JsonEvent2::ArrayEnd => self.end_array(),
+        }
+    }
+
+    pub fn start_object(&mut self) -> ParseResult<()> {
+        self.start_value()?;
+        self.write_str("{")?;
+        self.stack.push(WriterStackItem::Object(0));
+        Ok(())
+    }
+
+    pub fn end_object(&mut self) -> ParseResult<()> {
+        match self.stack.last() {
+            Some(WriterStackItem::Object(n)) if !self.after_name => {
+                let n = *n;
+                self.stack.pop();
+                self.end_container(n, "}")
+            },
+            _ => Err(Self::misplaced("end of object")),
+        }
+    }
+
+    pub fn start_array(&mut self) -> ParseResult<()> {
+        self.start_value()?;
+        self.write_str("[")?;
+        self.stack.push(WriterStackItem::Array(0));
+        Ok(())
+    }
+
+    pub fn end_array(&mut self) -> ParseResult<()> {
+        match self.stack.last() {
+            Some(WriterStackItem::Array(n)) => {
+                let n = *n;
+                self.stack.pop();
+                self.end_container(n, "]")
+            },
+            _ => Err(Self::misplaced("end of array")),
+        }
+    }
+
+    /// Name of an object member, the member's value must be written next
+    pub fn property_name(&mut self, name: &str) -> ParseResult<()> {
+        match self.stack.last_mut() {
+            Some(WriterStackItem::Object(n)) if !self.after_name => {
+                *n += 1;
+                let first = *n == 1;
+                self.next_line(first)?;
+                write_escaped(name, |s| self.write.write_all(s.as_bytes())).map_err(ParseErr::Io)?;
+                self.write_str(if self.indent.is_some() { ": " } else { ":" })?;
+                self.after_name = true;
+                Ok(())
+            },
+            _ => Err(Self::misplaced("property name")),
+        }
+    }
+
+    pub fn string(&mut self, s: &str) -> ParseResult<()> {
+        self.start_value()?;
+        write_escaped(s, |s| self.write.write_all(s.as_bytes())).map_err(ParseErr::Io)?;
+        self.end_value()
+    }
+
+    pub fn number(&mut self, n: &JsonNumber<'_>) -> ParseResult<()> {
+        self.start_value()?;
+        self.write_str(n.as_str())?;
+        self.end_value()
+    }
+
+    pub fn integer<I: Into<i128>>(&mut self, n: I) -> ParseResult<()> {
+        self.start_value()?;
+        write!(self.write, "{}", n.into()).map_err(ParseErr::Io)?;
+        self.end_value()
+    }
+
+    /// Writes the shortest text that reads back as the same float.  Infinity
+    /// and NaN can't be written as JSON, so are an error
+    pub fn float(&mut self, f: f64) -> ParseResult<()> {
+        if !f.is_finite() {
+            return Err(ParseErr::BadData(format!("Can't write {} as JSON", f)));
+        }
+        self.start_value()?;
+        // debug formatting gives an exponent for large and small values, rather than all the zeros
+        write!(self.write, "{:?}", f).map_err(ParseErr::Io)?;
+        self.end_value()
+    }
+
+    pub fn boolean(&mut self, b: bool) -> ParseResult<()> {
+        self.start_value()?;
+        self.write_str(if b { "true" } else { "false" })?;
+        self.end_value()
+    }
+
+    pub fn null(&mut self) -> ParseResult<()> {
+        self.start_value()?;
+        self.write_str("null")?;
+        self.end_value()
+    }
+
+    /// True once the top level value has been written, and all arrays and objects closed
+    pub fn is_complete(&self) -> bool {
+        self.done
+    }
+
+    /// Checks the document is complete, and hands back the output
+    pub fn finish(mut self) -> ParseResult<W> {
+        if !self.done {
+            return Err(ParseErr::BadData(String::from("JSON document is not complete")));
+        }
+        self.write.flush().map_err(ParseErr::Io)?;
+        Ok(self.write)
+    }
+
+    /// Checks a value can go here, and writes what goes before it
+    fn start_value(&mut self) -> ParseResult<()> {
+        match self.stack.last_mut() {
+            None if !self.done => Ok(()),
+            Some(WriterStackItem::Array(n)) => {
+                *n += 1;
+                let first = *n == 1;
+                self.next_line(first)
+            },
+            Some(WriterStackItem::Object(_)) if self.after_name => {
+                self.after_name = false;
+                Ok(())
+            },
+            _ => Err(Self::misplaced("value")),
+        }
+    }
+
+    /// After a value, we're done if it was the top level value
+    #[inline]
+    fn end_value(&mut self) -> ParseResult<()> {
+        if self.stack.is_empty() {
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    /// Closes an array or object that had `n` elements or members
+    fn end_container(&mut self, n: usize, close: &str) -> ParseResult<()> {
+        // empty ones stay on one line
+        if n != 0 {
+            self.new_line()?;
+        }
+        self.write_str(close)?;
+        self.end_value()
+    }
+
+    /// Separator before an element or member, and a new line when pretty printing
+    fn next_line(&mut self, first: bool) -> ParseResult<()> {
+        if !first {
+            self.write_str(",")?;
+        }
+        self.new_line()
+    }
+
+    fn new_line(&mut self) -> ParseResult<()> {
+        if let Some(indent) = self.indent {
+            self.write_str("\n")?;
+            for _i in 0..self.stack.len() {
+                self.write_str(indent)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn write_str(&mut self, s: &str) -> ParseResult<()> {
+        self.write.write_all(s.as_bytes()).map_err(ParseErr::Io)
+    }
+
+    fn misplaced(what: &str) -> ParseErr {
+        ParseErr::BadData(format!("A {} can't be written here", what))
+    }
+}
+
+
+/// Writes a string as JSON, with quotes and escapes, passing the pieces to `out`.
+/// Shared by everything that writes JSON
+pub(crate) fn write_escaped<E, F: FnMut(&str) -> Result<(), E>>(s: &str, mut out: F) -> Result<(), E> {
+    out("\"")?;
+    // copy runs of plain text in one go
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        // None for control chars without a short escape
+        let escape = match c {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            '\t' => Some("\\t"),
+            '\u{8}' => Some("\\b"),
+            '\u{c}' => Some("\\f"),
+            c if c < '\u{20}' => None,
+            _ => continue,
+        };
+        out(&s[start..i])?;
+        match escape {
+            Some(e) => out(e)?,
+            None => {
+                const HEX: &[u8; 16] = b"0123456789abcdef";
+                let u = [b'\\', b'u', b'0', b'0', HEX[c as usize >> 4], HEX[c as usize & 0xf]];
+                // all ASCII
+                out(std::str::from_utf8(&u).unwrap_or_default())?;
+            },
+        }
+        start = i + 1;
+    }
+    out(&s[start..])?;
+    out("\"")
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::json2::JsonParser;
+    use crate::json_value::JsonValue;
+    use std::io::Cursor;
+
+    fn compact<F: FnOnce(&mut JsonWriter<Vec<u8>>) -> ParseResult<()>>(f: F) -> ParseResult<String> {
+        let mut w = JsonWriter::new(Vec::new());
+        f(&mut w)?;
+        Ok(String::from_utf8(w.finish()?).unwrap())
+    }
+
+    /// Copies the events of the text through a writer
+    fn copy(text: &str, pretty: bool) -> ParseResult<String> {
+        let mut p = JsonParser::new(Cursor::new(text.as_bytes()));
+        let mut w = if pretty { JsonWriter::pretty(Vec::new()) } else { JsonWriter::new(Vec::new()) };
+        while let Some(e) = p.next_token()? {
+            w.write_event(&e)?;
+        }
+        Ok(String::from_utf8(w.finish()?).unwrap())
+    }
+
+    #[test]
+    fn test_write_compact() -> ParseResult<()> {
+        let s = compact(|w| {
+            w.start_object()?;
+            w.property_name("a")?;
+            w.start_array()?;
+            w.integer(1)?;
+            w.float(2.5)?;
+            w.float(1e300)?;
+            w.integer(u64::MAX)?;
+            w.end_array()?;
+            w.property_name("b")?;
+            w.start_object()?;
+            w.end_object()?;
+            w.property_name("c\"")?;
+            w.string("x\ny\u{1}\u{e9}")?;
+            w.property_name("d")?;
+            w.boolean(false)?;
+            w.property_name("e")?;
+            w.null()?;
+            w.end_object()
+        })?;
+        assert_eq!(r#"{"a":[1,2.5,1e300,18446744073709551615],"b":{},"c\"":"x\ny\u0001é","d":false,"e":null}"#, s);
+        assert_eq!("\"just a string\"", compact(|w| w.string("just a string"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_pretty() -> ParseResult<()> {
+        let s = copy(r#"{"a": [1, [], {}], "b": {"c": null}}"#, true)?;
+        assert_eq!("{\n  \"a\": [\n    1,\n    [],\n    {}\n  ],\n  \"b\": {\n    \"c\": null\n  }\n}", s);
+        assert_eq!("[]", copy("[]", true)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> ParseResult<()> {
+        let text = r#" { "name" : "parsely", "list" : [ 1, -0.5e-3, 12345678901234567890123, true, null, "😀\t" ],
+            "nested" : [ [ [ ] ], { "x" : { } } ], "" : "" } "#;
+        let original = JsonValue::parse(Cursor::new(text.as_bytes()))?;
+        for pretty in &[false, true] {
+            let written = copy(text, *pretty)?;
+            assert_eq!(original, JsonValue::parse(Cursor::new(written.as_bytes()))?, "{}", written);
+        }
+        assert_eq!(original.to_string(), copy(text, false)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bad_nesting() {
+        let mut w = JsonWriter::new(Vec::new());
+        assert!(w.end_array().is_err());
+        assert!(w.property_name("a").is_err());
+
+        let mut w = JsonWriter::new(Vec::new());
+        w.start_object().unwrap();
+        assert!(w.string("value with no name").is_err());
+        assert!(w.end_array().is_err());
+        w.property_name("a").unwrap();
+        assert!(w.property_name("b").is_err());
+        assert!(w.end_object().is_err());
+        w.integer(1).unwrap();
+        assert!(!w.is_complete());
+        w.end_object().unwrap();
+        assert!(w.is_complete());
+
+        // only one top level value
+        assert!(w.null().is_err());
+        assert!(w.float(f64::NAN).is_err());
+
+        let mut w = JsonWriter::new(Vec::new());
+        w.start_array().unwrap();
+        assert!(w.finish().is_err());
+    }
+}
@@ -6,5 +6,28 @@ pub mod internals;
 
 pub mod json2;
 
+pub mod json_number;
+
+pub mod json_value;
+
+pub mod json_writer;
+
+pub mod json_lines;
+
+pub mod json_push;
+
+pub mod json_pointer;
+
+pub mod json_path;
+
+#[cfg(feature = "async")]
+pub mod json_async;
+
+#[cfg(feature = "serde")]
+pub mod json_de;
+
+#[cfg(feature = "serde")]
+pub mod json_ser;
+
 // Parsing utility module based around parser combinators.
 // Also providing core parsing capability for common
\ No newline at end of file